@@ -0,0 +1,101 @@
+//! Axis-aligned bounding boxes.
+use crate::{Ray, Vec3};
+
+/// Axis-aligned bounding box.
+#[derive(Copy, Clone, Debug)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Construct a bounding box from its minimum and maximum corners.
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Smallest bounding box containing a set of points.
+    pub fn from_points<'a, I: IntoIterator<Item = &'a Vec3>>(points: I) -> Option<Self> {
+        points
+            .into_iter()
+            .map(|p| Self::new(*p, *p))
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// Bounding box of a flat disk.
+    pub fn disk(center: Vec3, normal: Vec3, radius: f64) -> Self {
+        // Extent along each axis is the radius scaled by the sine of its angle to the normal.
+        let n = normal.normalized();
+        let extent = Vec3::new(
+            (1.0 - n.x * n.x).max(0.0).sqrt(),
+            (1.0 - n.y * n.y).max(0.0).sqrt(),
+            (1.0 - n.z * n.z).max(0.0).sqrt(),
+        ) * radius;
+        Self::new(center - extent, center + extent)
+    }
+
+    /// Bounding box of a sphere.
+    pub fn sphere(center: Vec3, radius: f64) -> Self {
+        let extent = Vec3::one() * radius.abs();
+        Self::new(center - extent, center + extent)
+    }
+
+    /// Smallest bounding box containing both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        Self::new(self.min.min(&other.min), self.max.max(&other.max))
+    }
+
+    /// Extent of the box along each axis.
+    pub fn diagonal(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// Center of the box.
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Total area of the box faces.
+    pub fn surface_area(&self) -> f64 {
+        let d = self.diagonal();
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// Index of the axis along which the box is longest.
+    pub fn longest_axis(&self) -> usize {
+        let d = self.diagonal();
+        if d.x > d.y && d.x > d.z {
+            0
+        } else if d.y > d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Whether a ray passes through the box between two distances.
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.clip(ray, t_min, t_max).is_some()
+    }
+
+    /// Range of distances between two bounds over which a ray is inside the box (slab test).
+    pub fn clip(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        for axis in 0..3 {
+            let inverse = 1.0 / ray.direction[axis];
+            let mut t0 = (self.min[axis] - ray.point[axis]) * inverse;
+            let mut t1 = (self.max[axis] - ray.point[axis]) * inverse;
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // Written so that NaN (from zero times infinity) leaves the bounds unchanged.
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max < t_min {
+                return None;
+            }
+        }
+        Some((t_min, t_max))
+    }
+}
@@ -0,0 +1,104 @@
+//! Camera aperture shapes for lens sampling.
+use std::f64::consts::PI;
+use std::path::Path;
+
+use image::ImageResult;
+use rand::Rng;
+
+use crate::vector::{Axis, Vec3};
+
+/// Shape of a camera aperture, which determines the shape of out-of-focus highlights (bokeh).
+pub enum Aperture {
+    /// Circular aperture.
+    Circle,
+    /// Regular polygonal aperture formed by some number of diaphragm blades.
+    Polygon {
+        /// Number of blades (polygon vertices), at least three.
+        blades: usize,
+        /// Rotation of the polygon in degrees.
+        rotation: f64,
+    },
+    /// Aperture with transmission given by a grayscale image.
+    Mask(ApertureMask),
+}
+
+impl Aperture {
+    /// Randomized point within the aperture, scaled to fit the unit disk in the X-Y plane.
+    pub fn sample(&self) -> Vec3 {
+        match self {
+            Aperture::Circle => Vec3::random_in_unit_disk(Axis::Z),
+            Aperture::Polygon { blades, rotation } => {
+                let mut rng = rand::thread_rng();
+                let blades = (*blades).max(3);
+                let step = 2.0 * PI / blades as f64;
+                // Uniformly choose one of the equal area triangles fanning out from the center,
+                // then uniformly sample a point within it.
+                let i = rng.gen_range(0, blades) as f64;
+                let theta_a = rotation.to_radians() + step * i;
+                let theta_b = theta_a + step;
+                let (mut a, mut b) = (rng.gen::<f64>(), rng.gen::<f64>());
+                if a + b > 1.0 {
+                    a = 1.0 - a;
+                    b = 1.0 - b;
+                }
+                Vec3::new(
+                    a * theta_a.cos() + b * theta_b.cos(),
+                    a * theta_a.sin() + b * theta_b.sin(),
+                    0.0,
+                )
+            }
+            Aperture::Mask(mask) => mask.sample(),
+        }
+    }
+}
+
+/// Grayscale image aperture mask.
+///
+/// The image is stretched over the square bounding the unit disk, with brighter pixels
+/// transmitting more light.
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    values: Vec<f64>,
+    max: f64,
+}
+
+impl ApertureMask {
+    /// Construct a mask from row-major transmission values (top row first).
+    pub fn new(width: usize, height: usize, values: Vec<f64>) -> Self {
+        let max = values.iter().cloned().fold(0.0, f64::max);
+        Self {
+            width,
+            height,
+            values,
+            max,
+        }
+    }
+
+    /// Load a mask from an image file, converted to grayscale.
+    pub fn open<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        let image = image::open(path)?.into_luma8();
+        let (width, height) = image.dimensions();
+        let values = image.pixels().map(|p| p.0[0] as f64 / 255.0).collect();
+        Ok(Self::new(width as usize, height as usize, values))
+    }
+
+    /// Randomized point with density proportional to mask transmission.
+    ///
+    /// A mask without any transmission falls back to the unit disk.
+    fn sample(&self) -> Vec3 {
+        if self.max <= 0.0 {
+            return Vec3::random_in_unit_disk(Axis::Z);
+        }
+        let mut rng = rand::thread_rng();
+        loop {
+            let x: f64 = rng.gen();
+            let y: f64 = rng.gen();
+            let i = ((x * self.width as f64) as usize).min(self.width - 1);
+            let j = ((y * self.height as f64) as usize).min(self.height - 1);
+            if rng.gen::<f64>() * self.max < self.values[j * self.width + i] {
+                return Vec3::new(2.0 * x - 1.0, 1.0 - 2.0 * y, 0.0);
+            }
+        }
+    }
+}
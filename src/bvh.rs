@@ -0,0 +1,479 @@
+//! Bounding volume hierarchy for fast collision with many collidables.
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+use crate::aabb::Aabb;
+use crate::{Collidable, Collision, FindCollision, Ray, Vec3};
+
+/// Maximum number of collidables in a leaf node.
+const MAX_LEAF_SIZE: usize = 4;
+
+/// Number of bins into which centroids are sorted when evaluating surface area heuristic splits.
+const SAH_BINS: usize = 12;
+
+/// Cost of traversing a node relative to testing a collidable.
+const TRAVERSAL_COST: f64 = 0.125;
+
+/// Maximum depth of a hierarchy, bounding the stack of nodes left to traverse. Nodes this deep
+/// are made leaves.
+const MAX_DEPTH: usize = 64;
+
+/// Strategy by which nodes are split while building a hierarchy.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BuildMode {
+    /// Split at the median centroid along the longest axis. Fast to build, slower to traverse.
+    Median,
+    /// Split where the binned surface area heuristic estimates the cheapest traversal.
+    #[default]
+    Sah,
+}
+
+/// Statistics of a built hierarchy.
+#[derive(Copy, Clone, Debug)]
+pub struct BvhStats {
+    pub build_time: Duration,
+    pub node_count: usize,
+    pub leaf_count: usize,
+    /// Number of nodes on the longest path from the root to a leaf.
+    pub depth: usize,
+    /// Average number of collidables in a leaf.
+    pub average_leaf_size: f64,
+}
+
+impl Display for BvhStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "built in {:.3} ms, {} nodes ({} leaves), depth {}, {:.2} collidables per leaf",
+            self.build_time.as_secs_f64() * 1000.0,
+            self.node_count,
+            self.leaf_count,
+            self.depth,
+            self.average_leaf_size
+        )
+    }
+}
+
+/// Node of a flattened hierarchy.
+///
+/// Nodes are stored depth first, so the first child of a branch directly follows it.
+struct Node {
+    bounds: Aabb,
+    kind: NodeKind,
+}
+
+enum NodeKind {
+    /// Leaf over a range of the ordered collidables.
+    Leaf { first: usize, count: usize },
+    /// Branch with children split along an axis, the first holding the smaller centroids.
+    Branch { second: usize, axis: usize },
+}
+
+/// Collidable being placed into the hierarchy.
+struct Item {
+    bounds: Aabb,
+    centroid: Vec3,
+    /// Index of the collidable as given.
+    index: usize,
+}
+
+/// Move the items satisfying a predicate to the front, returning their number.
+fn partition<T>(items: &mut [T], predicate: impl Fn(&T) -> bool) -> usize {
+    let mut count = 0;
+    for i in 0..items.len() {
+        if predicate(&items[i]) {
+            items.swap(i, count);
+            count += 1;
+        }
+    }
+    count
+}
+
+struct Builder {
+    mode: BuildMode,
+    nodes: Vec<Node>,
+    /// Collidable indices in leaf order.
+    order: Vec<usize>,
+    depth: usize,
+}
+
+impl Builder {
+    /// Build a node and its descendants, returning its index.
+    fn build(&mut self, items: &mut [Item], depth: usize) -> usize {
+        let bounds = items
+            .iter()
+            .map(|item| item.bounds)
+            .reduce(|a, b| a.union(&b))
+            .expect("node without collidables");
+        let index = self.nodes.len();
+        self.depth = self.depth.max(depth);
+        self.nodes.push(Node {
+            bounds,
+            kind: NodeKind::Leaf { first: 0, count: 0 },
+        });
+
+        let split = self.split(items, &bounds).filter(|_| depth < MAX_DEPTH);
+        self.nodes[index].kind = match split {
+            Some((axis, middle)) => {
+                let (first, second) = items.split_at_mut(middle);
+                self.build(first, depth + 1);
+                NodeKind::Branch {
+                    second: self.build(second, depth + 1),
+                    axis,
+                }
+            }
+            None => {
+                let first = self.order.len();
+                self.order.extend(items.iter().map(|item| item.index));
+                NodeKind::Leaf {
+                    first,
+                    count: items.len(),
+                }
+            }
+        };
+        index
+    }
+
+    /// Partition items into two children, returning the split axis and the number of items in
+    /// the first child, or `None` if the items are better left in a leaf.
+    fn split(&self, items: &mut [Item], bounds: &Aabb) -> Option<(usize, usize)> {
+        if items.len() <= 1 {
+            return None;
+        }
+        let centroids = Aabb::from_points(items.iter().map(|item| &item.centroid))?;
+        let axis = centroids.longest_axis();
+        let min = centroids.min[axis];
+        let extent = centroids.diagonal()[axis];
+
+        // Bins cannot separate coincident centroids.
+        if self.mode == BuildMode::Median || extent <= 0.0 {
+            if items.len() <= MAX_LEAF_SIZE {
+                return None;
+            }
+            let middle = items.len() / 2;
+            items.select_nth_unstable_by(middle, |a, b| {
+                a.centroid[axis]
+                    .partial_cmp(&b.centroid[axis])
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            return Some((axis, middle));
+        }
+
+        let bin = |item: &Item| {
+            (((item.centroid[axis] - min) / extent * SAH_BINS as f64) as usize).min(SAH_BINS - 1)
+        };
+        let mut bins: [(Option<Aabb>, usize); SAH_BINS] = [(None, 0); SAH_BINS];
+        for item in items.iter() {
+            let (bin_bounds, count) = &mut bins[bin(item)];
+            *bin_bounds = Some(bin_bounds.map_or(item.bounds, |b| b.union(&item.bounds)));
+            *count += 1;
+        }
+
+        // Sweep from the right to find the area and count on the right of each split, then from
+        // the left to evaluate the cost of each split.
+        let area = |b: Option<Aabb>| b.map_or(0.0, |b| b.surface_area());
+        let union = |a: Option<Aabb>, b: Option<Aabb>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.union(&b)),
+            (a, b) => a.or(b),
+        };
+        let mut right = [(0.0, 0); SAH_BINS];
+        let mut right_bounds = None;
+        let mut right_count = 0;
+        for i in (1..SAH_BINS).rev() {
+            right_bounds = union(right_bounds, bins[i].0);
+            right_count += bins[i].1;
+            right[i] = (area(right_bounds), right_count);
+        }
+        let mut best = (f64::INFINITY, 0);
+        let mut left_bounds = None;
+        let mut left_count = 0;
+        for i in 0..SAH_BINS - 1 {
+            left_bounds = union(left_bounds, bins[i].0);
+            left_count += bins[i].1;
+            let (right_area, right_count) = right[i + 1];
+            if left_count == 0 || right_count == 0 {
+                continue;
+            }
+            let cost = TRAVERSAL_COST
+                + (left_count as f64 * area(left_bounds) + right_count as f64 * right_area)
+                    / bounds.surface_area().max(f64::MIN_POSITIVE);
+            if cost < best.0 {
+                best = (cost, i);
+            }
+        }
+
+        if items.len() <= MAX_LEAF_SIZE && best.0 >= items.len() as f64 {
+            return None;
+        }
+        Some((axis, partition(items, |item| bin(item) <= best.1)))
+    }
+}
+
+/// Collidable bounding volume hierarchy over a set of collidables.
+///
+/// Nodes are flattened into a single array which is traversed without recursion, visiting the
+/// nearer child of each branch first. Unbounded collidables (e.g. planes) are kept outside of the
+/// hierarchy and always tested.
+pub struct Bvh {
+    nodes: Vec<Node>,
+    collidables: Vec<Box<dyn Collidable>>,
+    unbounded: Vec<Box<dyn Collidable>>,
+    stats: BvhStats,
+}
+
+impl Bvh {
+    /// Build a hierarchy with the surface area heuristic.
+    pub fn new(collidables: Vec<Box<dyn Collidable>>) -> Self {
+        Self::with_mode(collidables, BuildMode::default())
+    }
+
+    /// Build a hierarchy with some split strategy.
+    pub fn with_mode(collidables: Vec<Box<dyn Collidable>>, mode: BuildMode) -> Self {
+        let start = Instant::now();
+        let mut bounded = Vec::new();
+        let mut items = Vec::new();
+        let mut unbounded = Vec::new();
+        for collidable in collidables {
+            match collidable.bounding_box() {
+                Some(bounds) => {
+                    items.push(Item {
+                        bounds,
+                        centroid: bounds.centroid(),
+                        index: bounded.len(),
+                    });
+                    bounded.push(Some(collidable));
+                }
+                None => unbounded.push(collidable),
+            }
+        }
+
+        let mut builder = Builder {
+            mode,
+            nodes: Vec::new(),
+            order: Vec::with_capacity(items.len()),
+            depth: 0,
+        };
+        if !items.is_empty() {
+            builder.build(&mut items, 1);
+        }
+        assert!(builder.depth <= MAX_DEPTH, "hierarchy too deep");
+        let collidables: Vec<Box<dyn Collidable>> = builder
+            .order
+            .iter()
+            .map(|&i| bounded[i].take().expect("collidable placed twice"))
+            .collect();
+
+        let leaf_count = builder
+            .nodes
+            .iter()
+            .filter(|node| matches!(node.kind, NodeKind::Leaf { .. }))
+            .count();
+        let stats = BvhStats {
+            build_time: start.elapsed(),
+            node_count: builder.nodes.len(),
+            leaf_count,
+            depth: builder.depth,
+            average_leaf_size: if leaf_count > 0 {
+                collidables.len() as f64 / leaf_count as f64
+            } else {
+                0.0
+            },
+        };
+        Self {
+            nodes: builder.nodes,
+            collidables,
+            unbounded,
+            stats,
+        }
+    }
+
+    /// Statistics of the hierarchy.
+    pub fn stats(&self) -> &BvhStats {
+        &self.stats
+    }
+}
+
+impl Collidable for Bvh {
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        let mut closest = self
+            .unbounded
+            .iter()
+            .find_closest_collision(ray, t_min, t_max);
+        let mut t_max = closest.as_ref().map_or(t_max, |c| c.t);
+        if self.nodes.is_empty() {
+            return closest;
+        }
+
+        // Each level of the hierarchy leaves at most one node to traverse later.
+        let mut stack = [0; MAX_DEPTH];
+        let mut stack_len = 0;
+        let mut index = 0;
+        loop {
+            let node = &self.nodes[index];
+            if node.bounds.hit(ray, t_min, t_max) {
+                match node.kind {
+                    NodeKind::Leaf { first, count } => {
+                        if let Some(collision) = self.collidables[first..first + count]
+                            .iter()
+                            .find_closest_collision(ray, t_min, t_max)
+                        {
+                            t_max = collision.t;
+                            closest = Some(collision);
+                        }
+                    }
+                    NodeKind::Branch { second, axis } => {
+                        if ray.direction[axis] < 0.0 {
+                            stack[stack_len] = index + 1;
+                            index = second;
+                        } else {
+                            stack[stack_len] = second;
+                            index += 1;
+                        }
+                        stack_len += 1;
+                        continue;
+                    }
+                }
+            }
+            if stack_len == 0 {
+                return closest;
+            }
+            stack_len -= 1;
+            index = stack[stack_len];
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if self.unbounded.is_empty() {
+            self.nodes.first().map(|root| root.bounds)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collide::{Plane, Sphere};
+    use crate::material::Lambert;
+    use crate::Material;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::rc::Rc;
+
+    /// Random spheres, with some overlapping or coincident.
+    fn spheres(rng: &mut StdRng, count: usize) -> Vec<(Vec3, f64)> {
+        let mut spheres: Vec<(Vec3, f64)> = (0..count)
+            .map(|_| {
+                let center = Vec3::new(
+                    rng.gen_range(-10.0, 10.0),
+                    rng.gen_range(-10.0, 10.0),
+                    rng.gen_range(-10.0, 10.0),
+                );
+                (center, rng.gen_range(0.05, 1.0))
+            })
+            .collect();
+        spheres.extend(spheres.clone().into_iter().take(count / 10));
+        spheres
+    }
+
+    fn collidables(spheres: &[(Vec3, f64)], plane: bool) -> Vec<Box<dyn Collidable>> {
+        let material: Rc<dyn Material> = Rc::new(Lambert::new(Vec3::new(0.5, 0.5, 0.5)));
+        let mut collidables: Vec<Box<dyn Collidable>> = spheres
+            .iter()
+            .map(|&(center, radius)| {
+                Box::new(Sphere::new(center, radius, material.clone())) as Box<dyn Collidable>
+            })
+            .collect();
+        if plane {
+            collidables.push(Box::new(Plane::new(
+                Vec3::new(0.0, -8.0, 0.0),
+                Vec3::new(0.1, 1.0, 0.0),
+                material,
+            )));
+        }
+        collidables
+    }
+
+    #[test]
+    fn collision_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let spheres = spheres(&mut rng, 400);
+        for &plane in &[false, true] {
+            let brute_force = collidables(&spheres, plane);
+            for &mode in &[BuildMode::Median, BuildMode::Sah] {
+                let bvh = Bvh::with_mode(collidables(&spheres, plane), mode);
+                let mut hits = 0;
+                for _ in 0..2000 {
+                    let point = Vec3::new(
+                        rng.gen_range(-15.0, 15.0),
+                        rng.gen_range(-15.0, 15.0),
+                        rng.gen_range(-15.0, 15.0),
+                    );
+                    let direction = Vec3::new(
+                        rng.gen_range(-1.0, 1.0),
+                        rng.gen_range(-1.0, 1.0),
+                        rng.gen_range(-1.0, 1.0),
+                    );
+                    let ray = Ray::new(point, direction);
+                    let t_max = rng.gen_range(1.0, 50.0);
+                    let expected = brute_force
+                        .iter()
+                        .find_closest_collision(&ray, 1e-6, t_max)
+                        .map(|c| c.t);
+                    let found = bvh.collision(&ray, 1e-6, t_max).map(|c| c.t);
+                    assert_eq!(found, expected, "{:?} with {:?}", ray, mode);
+                    hits += found.is_some() as usize;
+                }
+                assert!(hits > 200, "only {} hits", hits);
+            }
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let spheres = spheres(&mut rng, 1000);
+        for &mode in &[BuildMode::Median, BuildMode::Sah] {
+            let bvh = Bvh::with_mode(collidables(&spheres, false), mode);
+            let stats = bvh.stats();
+            assert_eq!(stats.node_count, 2 * stats.leaf_count - 1);
+            let placed = stats.average_leaf_size * stats.leaf_count as f64;
+            assert!((placed - spheres.len() as f64).abs() < 1e-6);
+            assert!(stats.depth > 1 && stats.depth < stats.node_count);
+            assert!(bvh.bounding_box().is_some());
+        }
+
+        // Unbounded collidables are not placed in the hierarchy.
+        let bvh = Bvh::new(collidables(&spheres[..1], true));
+        assert_eq!((bvh.stats().node_count, bvh.stats().leaf_count), (1, 1));
+        assert!(bvh.bounding_box().is_none());
+
+        // Spheres spaced exponentially are split one at a time, until the depth is limited.
+        let material: Rc<dyn Material> = Rc::new(Lambert::new(Vec3::new(0.5, 0.5, 0.5)));
+        let deep: Vec<Box<dyn Collidable>> = (0..200)
+            .map(|i| {
+                let x = 2f64.powi(i);
+                Box::new(Sphere::new(
+                    Vec3::new(x, 0.0, 0.0),
+                    x / 4.0,
+                    material.clone(),
+                )) as Box<dyn Collidable>
+            })
+            .collect();
+        let bvh = Bvh::new(deep);
+        assert_eq!(bvh.stats().depth, MAX_DEPTH);
+        for i in &[0, 100, 199] {
+            let x = 2f64.powi(*i);
+            let ray = Ray::new(Vec3::new(x, -x, 0.0), Vec3::new(0.0, 1.0, 0.0));
+            let collision = bvh.collision(&ray, 0.0, f64::INFINITY).unwrap();
+            assert!((collision.t / x - 0.75).abs() < 1e-9);
+        }
+
+        let empty = Bvh::new(Vec::new());
+        assert_eq!(empty.stats().node_count, 0);
+        assert_eq!(empty.stats().average_leaf_size, 0.0);
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, 1.0));
+        assert!(empty.collision(&ray, 0.0, f64::INFINITY).is_none());
+    }
+}
@@ -1,9 +1,84 @@
 //! Camera encapsulation.
+use std::f64::consts::PI;
+
+use crate::aperture::Aperture;
 use crate::ray::Ray;
-use crate::vector::{Axis, Vec3};
+use crate::vector::Vec3;
+use crate::world::World;
+
+/// A camera which generates rays through an image plane.
+pub trait Camera {
+    /// Generate a ray through the image plane.
+    ///
+    /// * `u` - Horizontal image plane coordinate in [0.0, 1.0] (left to right).
+    /// * `v` - Vertical image plane coordinate in [0.0, 1.0] (bottom to top).
+    ///
+    /// Returns `None` for image plane points the camera does not cover (rendered as black).
+    fn get_ray(&self, u: f64, v: f64) -> Option<Ray>;
+}
+
+/// Orthonormal horizontal and vertical view vectors for a backwards view direction and up vector.
+fn view_basis(direction: &Vec3, up: &Vec3) -> (Vec3, Vec3) {
+    let u = up.cross(direction).normalized();
+    let v = direction.cross(&u);
+    (u, v)
+}
+
+/// Physical camera parameters from which field of view and aperture are derived.
+///
+/// Scene units are taken to be meters.
+#[derive(Copy, Clone, Debug)]
+pub struct PhysicalLens {
+    /// Focal length in millimeters.
+    pub focal_length: f64,
+    /// Sensor width in millimeters.
+    pub sensor_width: f64,
+    /// Sensor height in millimeters.
+    pub sensor_height: f64,
+    /// Ratio of focal length to aperture diameter (f-stop).
+    pub f_number: f64,
+}
+
+impl PhysicalLens {
+    /// Construct physical lens parameters.
+    ///
+    /// * `focal_length` - Focal length in millimeters.
+    /// * `sensor_width` - Sensor width in millimeters.
+    /// * `sensor_height` - Sensor height in millimeters.
+    /// * `f_number` - Ratio of focal length to aperture diameter.
+    pub fn new(focal_length: f64, sensor_width: f64, sensor_height: f64, f_number: f64) -> Self {
+        Self {
+            focal_length,
+            sensor_width,
+            sensor_height,
+            f_number,
+        }
+    }
+
+    /// Full frame 35mm sensor (36mm by 24mm).
+    pub fn full_frame(focal_length: f64, f_number: f64) -> Self {
+        Self::new(focal_length, 36.0, 24.0, f_number)
+    }
 
-/// Camera encapsulation.
-pub struct Camera {
+    /// Vertical field of view in degrees for an image of some aspect ratio.
+    ///
+    /// The image is fit inside the sensor, cropping whichever sensor dimension does not match the
+    /// image aspect ratio.
+    pub fn vertical_fov(&self, aspect_ratio: f64) -> f64 {
+        let film_height = self.sensor_height.min(self.sensor_width / aspect_ratio);
+        2.0 * (film_height / (2.0 * self.focal_length))
+            .atan()
+            .to_degrees()
+    }
+
+    /// Aperture (lens) diameter in scene units.
+    pub fn aperture(&self) -> f64 {
+        self.focal_length / self.f_number / 1000.0
+    }
+}
+
+/// Perspective thin-lens camera.
+pub struct PerspectiveCamera {
     // User set state:
     origin: Vec3,
     direction: Vec3,
@@ -11,14 +86,18 @@ pub struct Camera {
     vertical_fov: f64,
     aspect_ratio: f64,
     aperture: f64,
+    aperture_shape: Aperture,
     focus_distance: f64,
+    lens: Option<PhysicalLens>,
     // Calculated state:
+    u: Vec3,
+    v: Vec3,
     horizontal: Vec3,
     vertical: Vec3,
     lower_left_corner: Vec3,
 }
 
-impl Camera {
+impl PerspectiveCamera {
     /// Construct a new camera.
     pub fn new() -> Self {
         Self {
@@ -28,7 +107,11 @@ impl Camera {
             vertical_fov: 0.0,
             aspect_ratio: 0.0,
             aperture: 0.0,
+            aperture_shape: Aperture::Circle,
             focus_distance: 0.0,
+            lens: None,
+            u: Vec3::zero(),
+            v: Vec3::zero(),
             horizontal: Vec3::zero(),
             vertical: Vec3::zero(),
             lower_left_corner: Vec3::zero(),
@@ -36,14 +119,24 @@ impl Camera {
     }
 
     /// Update computed camera state.
+    ///
+    /// If physical lens parameters are set, the vertical field of view and aperture are derived
+    /// from them.
     pub fn update(&mut self) -> &mut Self {
-        let Camera {
+        if let Some(lens) = self.lens {
+            self.vertical_fov = lens.vertical_fov(self.aspect_ratio);
+            self.aperture = lens.aperture();
+        }
+
+        let PerspectiveCamera {
             ref origin,
             ref direction,
             ref up,
             ref vertical_fov,
             ref aspect_ratio,
             ref focus_distance,
+            u,
+            v,
             horizontal,
             vertical,
             lower_left_corner,
@@ -54,11 +147,12 @@ impl Camera {
         let h = (theta / 2.0).tan();
         let viewport_height = 2.0 * h;
         let viewport_width = aspect_ratio * viewport_height;
-        let u = up.cross(&direction).normalized();
-        let v = direction.cross(&u);
+        let (basis_u, basis_v) = view_basis(direction, up);
 
-        *horizontal = u * viewport_width * focus_distance;
-        *vertical = v * viewport_height * focus_distance;
+        *u = basis_u;
+        *v = basis_v;
+        *horizontal = basis_u * viewport_width * focus_distance;
+        *vertical = basis_v * viewport_height * focus_distance;
         *lower_left_corner =
             origin - (*horizontal) * 0.5 - (*vertical) * 0.5 - direction * focus_distance;
         self
@@ -94,18 +188,318 @@ impl Camera {
         self
     }
 
+    /// Set the shape of the aperture (circular by default).
+    pub fn set_aperture_shape(&mut self, aperture_shape: Aperture) -> &mut Self {
+        self.aperture_shape = aperture_shape;
+        self
+    }
+
     pub fn set_focus_distance(&mut self, focus_distance: f64) -> &mut Self {
         self.focus_distance = focus_distance;
         self
     }
 
-    pub fn get_ray(&self, u: f64, v: f64) -> Ray {
+    /// Set physical lens parameters, overriding the vertical field of view and aperture.
+    pub fn set_lens(&mut self, lens: PhysicalLens) -> &mut Self {
+        self.lens = Some(lens);
+        self
+    }
+
+    /// Focus on the first collision along the view direction through the image center.
+    ///
+    /// The focus distance is left unchanged if nothing is hit. Computed state is updated.
+    pub fn autofocus(&mut self, world: &World) -> &mut Self {
+        let ray = Ray::new(self.origin, -self.direction);
+        if let Some(collision) = world.closest_collision(&ray) {
+            self.focus_distance = collision.t;
+        }
+        self.update()
+    }
+}
+
+impl Default for PerspectiveCamera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn get_ray(&self, u: f64, v: f64) -> Option<Ray> {
         let lens_radius = self.aperture / 2.0;
-        let rd = Vec3::random_in_unit_disk(Axis::Z) * lens_radius;
-        let offset = u * rd.x + v * rd.y;
-        Ray::new(
+        let rd = self.aperture_shape.sample() * lens_radius;
+        let offset = self.u * rd.x + self.v * rd.y;
+        Some(Ray::new(
             self.origin + offset,
             self.lower_left_corner + self.horizontal * u + self.vertical * v - self.origin - offset,
-        )
+        ))
+    }
+}
+
+/// Orthographic camera.
+///
+/// All rays share the view direction and originate from a rectangle of the configured view
+/// height, so objects keep their size regardless of distance.
+pub struct OrthographicCamera {
+    // User set state:
+    origin: Vec3,
+    direction: Vec3,
+    up: Vec3,
+    view_height: f64,
+    aspect_ratio: f64,
+    // Calculated state:
+    horizontal: Vec3,
+    vertical: Vec3,
+    lower_left_corner: Vec3,
+}
+
+impl OrthographicCamera {
+    /// Construct a new camera.
+    pub fn new() -> Self {
+        Self {
+            origin: Vec3::zero(),
+            direction: Vec3::zero(),
+            up: Vec3::zero(),
+            view_height: 0.0,
+            aspect_ratio: 0.0,
+            horizontal: Vec3::zero(),
+            vertical: Vec3::zero(),
+            lower_left_corner: Vec3::zero(),
+        }
+    }
+
+    /// Update computed camera state.
+    pub fn update(&mut self) -> &mut Self {
+        let (u, v) = view_basis(&self.direction, &self.up);
+        self.horizontal = u * self.view_height * self.aspect_ratio;
+        self.vertical = v * self.view_height;
+        self.lower_left_corner = self.origin - self.horizontal * 0.5 - self.vertical * 0.5;
+        self
+    }
+
+    pub fn look_from(&mut self, point: Vec3) -> &mut Self {
+        self.origin = point;
+        self
+    }
+
+    pub fn look_at(&mut self, point: Vec3) -> &mut Self {
+        self.direction = (self.origin - point).normalized();
+        self
+    }
+
+    pub fn set_up(&mut self, up: Vec3) -> &mut Self {
+        self.up = up;
+        self
+    }
+
+    /// Set the height of the view rectangle in world units.
+    pub fn set_view_height(&mut self, view_height: f64) -> &mut Self {
+        self.view_height = view_height;
+        self
+    }
+
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f64) -> &mut Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+}
+
+impl Default for OrthographicCamera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn get_ray(&self, u: f64, v: f64) -> Option<Ray> {
+        Some(Ray::new(
+            self.lower_left_corner + self.horizontal * u + self.vertical * v,
+            -self.direction,
+        ))
+    }
+}
+
+/// Equirectangular (latitude-longitude) 360° panoramic camera.
+///
+/// The horizontal image axis spans the full longitude range centered on the view direction and
+/// the vertical axis spans latitudes from straight down to straight up. Images should have an
+/// aspect ratio of 2:1.
+pub struct EquirectangularCamera {
+    // User set state:
+    origin: Vec3,
+    direction: Vec3,
+    up: Vec3,
+    // Calculated state:
+    u: Vec3,
+    v: Vec3,
+}
+
+impl EquirectangularCamera {
+    /// Construct a new camera.
+    pub fn new() -> Self {
+        Self {
+            origin: Vec3::zero(),
+            direction: Vec3::zero(),
+            up: Vec3::zero(),
+            u: Vec3::zero(),
+            v: Vec3::zero(),
+        }
+    }
+
+    /// Update computed camera state.
+    pub fn update(&mut self) -> &mut Self {
+        let (u, v) = view_basis(&self.direction, &self.up);
+        self.u = u;
+        self.v = v;
+        self
+    }
+
+    pub fn look_from(&mut self, point: Vec3) -> &mut Self {
+        self.origin = point;
+        self
+    }
+
+    pub fn look_at(&mut self, point: Vec3) -> &mut Self {
+        self.direction = (self.origin - point).normalized();
+        self
+    }
+
+    pub fn set_up(&mut self, up: Vec3) -> &mut Self {
+        self.up = up;
+        self
+    }
+}
+
+impl Default for EquirectangularCamera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Camera for EquirectangularCamera {
+    fn get_ray(&self, u: f64, v: f64) -> Option<Ray> {
+        let longitude = (u - 0.5) * 2.0 * PI;
+        let latitude = (v - 0.5) * PI;
+        let horizontal = self.u * longitude.sin() - self.direction * longitude.cos();
+        Some(Ray::new(
+            self.origin,
+            horizontal * latitude.cos() + self.v * latitude.sin(),
+        ))
+    }
+}
+
+/// Fisheye lens mapping from angle off the view axis to distance from the image circle center.
+#[derive(Copy, Clone, Debug)]
+pub enum FisheyeProjection {
+    /// Distance proportional to the angle (`r = f θ`).
+    Equidistant,
+    /// Equal solid angles map to equal image areas (`r = 2 f sin(θ / 2)`).
+    Equisolid,
+}
+
+/// Fisheye camera.
+///
+/// The image circle is inscribed in the shorter image dimension; points outside it are not
+/// covered by the camera.
+pub struct FisheyeCamera {
+    // User set state:
+    origin: Vec3,
+    direction: Vec3,
+    up: Vec3,
+    field_of_view: f64,
+    aspect_ratio: f64,
+    projection: FisheyeProjection,
+    // Calculated state:
+    u: Vec3,
+    v: Vec3,
+}
+
+impl FisheyeCamera {
+    /// Construct a new camera.
+    pub fn new() -> Self {
+        Self {
+            origin: Vec3::zero(),
+            direction: Vec3::zero(),
+            up: Vec3::zero(),
+            field_of_view: 180.0,
+            aspect_ratio: 0.0,
+            projection: FisheyeProjection::Equidistant,
+            u: Vec3::zero(),
+            v: Vec3::zero(),
+        }
+    }
+
+    /// Update computed camera state.
+    pub fn update(&mut self) -> &mut Self {
+        let (u, v) = view_basis(&self.direction, &self.up);
+        self.u = u;
+        self.v = v;
+        self
+    }
+
+    pub fn look_from(&mut self, point: Vec3) -> &mut Self {
+        self.origin = point;
+        self
+    }
+
+    pub fn look_at(&mut self, point: Vec3) -> &mut Self {
+        self.direction = (self.origin - point).normalized();
+        self
+    }
+
+    pub fn set_up(&mut self, up: Vec3) -> &mut Self {
+        self.up = up;
+        self
+    }
+
+    /// Set the field of view across the image circle in degrees (up to 360°).
+    pub fn set_field_of_view(&mut self, field_of_view: f64) -> &mut Self {
+        self.field_of_view = field_of_view;
+        self
+    }
+
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f64) -> &mut Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    pub fn set_projection(&mut self, projection: FisheyeProjection) -> &mut Self {
+        self.projection = projection;
+        self
+    }
+}
+
+impl Default for FisheyeCamera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Camera for FisheyeCamera {
+    fn get_ray(&self, u: f64, v: f64) -> Option<Ray> {
+        // Image plane coordinates scaled such that the image circle has unit radius.
+        let (x, y) = if self.aspect_ratio >= 1.0 {
+            ((2.0 * u - 1.0) * self.aspect_ratio, 2.0 * v - 1.0)
+        } else {
+            (2.0 * u - 1.0, (2.0 * v - 1.0) / self.aspect_ratio)
+        };
+        let r = (x * x + y * y).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+
+        let theta_max = self.field_of_view.to_radians() / 2.0;
+        let theta = match self.projection {
+            FisheyeProjection::Equidistant => r * theta_max,
+            FisheyeProjection::Equisolid => 2.0 * (r * (theta_max / 2.0).sin()).min(1.0).asin(),
+        };
+        let radial = if r > 0.0 {
+            (self.u * x + self.v * y) / r
+        } else {
+            Vec3::zero()
+        };
+        Some(Ray::new(
+            self.origin,
+            radial * theta.sin() - self.direction * theta.cos(),
+        ))
     }
 }
@@ -1,10 +1,16 @@
 //! Collision traits and simple shape implementations.
+use std::f64::consts::PI;
 use std::rc::Rc;
 use std::slice::Iter;
 
-use crate::{Material, Ray, Vec3};
+use rand::Rng;
+
+use crate::aabb::Aabb;
+use crate::light::Surface;
+use crate::{Material, Ray, Texture, Vec3};
 
 /// Collision record.
+#[derive(Clone)]
 pub struct Collision {
     /// Distance along ray at which collision occurred.
     pub t: f64,
@@ -12,9 +18,23 @@ pub struct Collision {
     /// Point in space at which collision occurred.
     pub point: Vec3,
 
-    /// Normal from collided object.
+    /// Shading normal, facing against the colliding ray.
     pub normal: Vec3,
 
+    /// Normal of the collided surface itself, facing against the colliding ray.
+    pub geometric_normal: Vec3,
+
+    /// Unit tangent to the surface along increasing `u`, used with the outward normal to orient
+    /// tangent space.
+    pub tangent: Vec3,
+
+    /// Surface (texture) coordinates at which collision occurred.
+    pub u: f64,
+    pub v: f64,
+
+    /// Color interpolated from the vertices of the collided surface, if it has any.
+    pub color: Option<Vec3>,
+
     /// Material of collided object.
     pub material: Rc<dyn Material>,
 
@@ -29,12 +49,16 @@ impl Collision {
     /// * `t` - Distance along which at which collision occurred.
     /// * `p` - Point in space at which collision occurred.
     /// * `outward_normal` - Unit vector at the point of collision normal to the surface of the collided object.
+    /// * `uv` - Surface coordinates at which collision occurred.
+    /// * `tangent` - Vector tangent to the surface along increasing `u` (or zero if undefined).
     /// * `material` - Material of the collided object.
-    fn new(
+    pub fn new(
         ray: &Ray,
         t: f64,
         point: Vec3,
         outward_normal: Vec3,
+        (u, v): (f64, f64),
+        tangent: Vec3,
         material: Rc<dyn Material>,
     ) -> Self {
         let front_face = ray.direction.dot(&outward_normal) < 0.0;
@@ -43,10 +67,22 @@ impl Collision {
         } else {
             -outward_normal
         };
+        // Fall back to an arbitrary tangent where the surface coordinates are degenerate.
+        let tangent = tangent - outward_normal * tangent.dot(&outward_normal);
+        let tangent = if tangent.magnitude_squared() > 1e-12 {
+            tangent.normalized()
+        } else {
+            outward_normal.orthonormal_basis().0
+        };
         Self {
             t,
             point,
             normal,
+            geometric_normal: normal,
+            tangent,
+            u,
+            v,
+            color: None,
             material,
             front_face,
         }
@@ -60,6 +96,43 @@ pub trait Collidable {
     /// * `t_min` - Lower bound on the distance at which collisions are considered.
     /// * `t_max` - Upper bound on the distance at which collisions are considered.
     fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision>;
+
+    /// Box bounding `self`, or `None` if it is unbounded.
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+
+    /// All spans, in order, over which the (infinite) line of `ray` is inside `self`, at any
+    /// distance including negative ones.
+    ///
+    /// `None` if `self` does not enclose a volume, as required for constructive solid geometry.
+    fn intervals(&self, _ray: &Ray) -> Option<Vec<Interval>> {
+        None
+    }
+}
+
+/// Span along a ray inside a closed collidable.
+pub struct Interval {
+    /// Collision at which the ray enters.
+    pub entry: Collision,
+
+    /// Collision at which the ray exits.
+    pub exit: Collision,
+}
+
+impl Interval {
+    /// Pair up the collisions of a ray with a closed surface into spans inside it.
+    ///
+    /// An unpaired final collision (from a grazing ray) is discarded.
+    pub fn pairs(mut collisions: Vec<Collision>) -> Vec<Interval> {
+        collisions.sort_by(|x, y| x.t.partial_cmp(&y.t).unwrap_or(std::cmp::Ordering::Equal));
+        let mut collisions = collisions.into_iter();
+        let mut intervals = Vec::new();
+        while let (Some(entry), Some(exit)) = (collisions.next(), collisions.next()) {
+            intervals.push(Interval { entry, exit });
+        }
+        intervals
+    }
 }
 
 /// Iterator addaptor to find
@@ -77,6 +150,7 @@ impl FindCollision for Iter<'_, Box<dyn Collidable>>
 }
 
 /// Collidable sphere.
+#[derive(Clone)]
 pub struct Sphere {
     center: Vec3,
     radius: f64,
@@ -91,10 +165,21 @@ impl Sphere {
             material,
         }
     }
+
+    /// Surface coordinates of a point on the unit sphere.
+    ///
+    /// `u` increases with the angle about the Y axis starting from -X, and `v` from the bottom
+    /// (-Y) to the top (+Y).
+    fn uv(p: &Vec3) -> (f64, f64) {
+        let theta = (-p.y).clamp(-1.0, 1.0).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
 }
 
-impl Collidable for Sphere {
-    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+impl Sphere {
+    /// Distances along a ray at which it crosses the surface, in increasing order.
+    fn roots(&self, ray: &Ray) -> Option<(f64, f64)> {
         let oc = ray.point - self.center;
         let a = ray.direction.magnitude_squared();
         let half_b = oc.dot(&ray.direction);
@@ -103,23 +188,249 @@ impl Collidable for Sphere {
 
         if discriminant > 0.0 {
             let root = discriminant.sqrt();
-            for &t in &[(-half_b - root) / a, (-half_b + root) / a] {
-                if t < t_max && t > t_min {
-                    let p = ray.at(t);
-                    let outward_normal = (p - self.center) / self.radius;
-                    return Some(Collision::new(
-                        &ray,
-                        t,
-                        p,
-                        outward_normal,
-                        self.material.clone(),
-                    ));
-                }
-            }
+            Some(((-half_b - root) / a, (-half_b + root) / a))
+        } else {
+            None
+        }
+    }
+
+    /// Collision record at a distance along a ray which crosses the surface.
+    fn collision_at(&self, ray: &Ray, t: f64) -> Collision {
+        let p = ray.at(t);
+        let outward_normal = (p - self.center) / self.radius;
+        let unit = (p - self.center) / self.radius.abs();
+        // Derivative of the point with respect to `u` (about the Y axis).
+        let tangent = Vec3::new(unit.z, 0.0, -unit.x);
+        Collision::new(
+            ray,
+            t,
+            p,
+            outward_normal,
+            Self::uv(&unit),
+            tangent,
+            self.material.clone(),
+        )
+    }
+}
+
+impl Collidable for Sphere {
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        let (t0, t1) = self.roots(ray)?;
+        [t0, t1]
+            .iter()
+            .find(|&&t| t < t_max && t > t_min)
+            .map(|&t| self.collision_at(ray, t))
+    }
+
+    /// Spheres with negative radius enclose everything outside of them, so have no intervals.
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval>> {
+        if self.radius < 0.0 {
+            return None;
         }
+        Some(match self.roots(ray) {
+            Some((t0, t1)) => vec![Interval {
+                entry: self.collision_at(ray, t0),
+                exit: self.collision_at(ray, t1),
+            }],
+            None => vec![],
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::sphere(self.center, self.radius))
+    }
+}
+
+impl Surface for Sphere {
+    fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn sample_point(&self, u0: f64, u1: f64) -> (Vec3, Vec3) {
+        let y = 1.0 - 2.0 * u0;
+        let r = (1.0 - y * y).max(0.0).sqrt();
+        let phi = 2.0 * PI * u1;
+        let unit = Vec3::new(r * phi.cos(), y, r * phi.sin());
+        (
+            self.center + unit * self.radius.abs(),
+            unit * self.radius.signum(),
+        )
+    }
+}
+
+/// Collidable infinite plane.
+///
+/// Surface coordinates are distances along two arbitrary perpendicular directions in the plane.
+pub struct Plane {
+    point: Vec3,
+    normal: Vec3,
+    tangent: Vec3,
+    bitangent: Vec3,
+    material: Rc<dyn Material>,
+}
+
+impl Plane {
+    /// Construct a plane.
+    ///
+    /// * `point` - Any point on the plane, at which surface coordinates are zero.
+    /// * `normal` - Direction normal to the front face of the plane.
+    pub fn new(point: Vec3, normal: Vec3, material: Rc<dyn Material>) -> Self {
+        let normal = normal.normalized();
+        let (tangent, bitangent) = normal.orthonormal_basis();
+        Self {
+            point,
+            normal,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+}
+
+/// Distance along a ray to a plane, if it lies within bounds.
+fn plane_distance(ray: &Ray, point: &Vec3, normal: &Vec3, t_min: f64, t_max: f64) -> Option<f64> {
+    let denominator = ray.direction.dot(normal);
+    if denominator == 0.0 {
+        return None;
+    }
+    let t = (point - ray.point).dot(normal) / denominator;
+    if t < t_max && t > t_min {
+        Some(t)
+    } else {
         None
     }
 }
+
+impl Collidable for Plane {
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        let t = plane_distance(ray, &self.point, &self.normal, t_min, t_max)?;
+        let p = ray.at(t);
+        let offset = p - self.point;
+        Some(Collision::new(
+            ray,
+            t,
+            p,
+            self.normal,
+            (offset.dot(&self.tangent), offset.dot(&self.bitangent)),
+            self.tangent,
+            self.material.clone(),
+        ))
+    }
+}
+
+/// Collidable flat disk.
+///
+/// Surface coordinates map the square enclosing the disk to [0.0, 1.0].
+#[derive(Clone)]
+pub struct Disk {
+    center: Vec3,
+    normal: Vec3,
+    radius: f64,
+    tangent: Vec3,
+    bitangent: Vec3,
+    material: Rc<dyn Material>,
+}
+
+impl Disk {
+    /// Construct a disk.
+    ///
+    /// * `center` - Center of the disk.
+    /// * `normal` - Direction normal to the front face of the disk.
+    /// * `radius` - Radius of the disk.
+    pub fn new(center: Vec3, normal: Vec3, radius: f64, material: Rc<dyn Material>) -> Self {
+        let normal = normal.normalized();
+        let (tangent, bitangent) = normal.orthonormal_basis();
+        Self {
+            center,
+            normal,
+            radius,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+}
+
+impl Collidable for Disk {
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        let t = plane_distance(ray, &self.center, &self.normal, t_min, t_max)?;
+        let p = ray.at(t);
+        let offset = p - self.center;
+        if offset.magnitude_squared() > self.radius * self.radius {
+            return None;
+        }
+        let u = 0.5 + offset.dot(&self.tangent) / (2.0 * self.radius);
+        let v = 0.5 + offset.dot(&self.bitangent) / (2.0 * self.radius);
+        Some(Collision::new(
+            ray,
+            t,
+            p,
+            self.normal,
+            (u, v),
+            self.tangent,
+            self.material.clone(),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::disk(self.center, self.normal, self.radius))
+    }
+}
+
+impl Surface for Disk {
+    fn area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+
+    fn sample_point(&self, u0: f64, u1: f64) -> (Vec3, Vec3) {
+        let r = self.radius * u0.sqrt();
+        let phi = 2.0 * PI * u1;
+        let point = self.center + self.tangent * (r * phi.cos()) + self.bitangent * (r * phi.sin());
+        (point, self.normal)
+    }
+}
+
+/// Collidable with holes cut out by an opacity (alpha) mask, such as a leaf or a fence.
+///
+/// Collisions where the mask is partially transparent are kept at random with probability equal
+/// to the opacity, so that camera and shadow rays alike see through on average.
+pub struct Cutout {
+    collidable: Box<dyn Collidable>,
+    opacity: Rc<dyn Texture>,
+}
+
+impl Cutout {
+    /// Construct a cut out collidable.
+    ///
+    /// * `collidable` - Collidable in which holes are cut.
+    /// * `opacity` - Opacity in [0.0, 1.0], read from the first (X) component.
+    pub fn new(collidable: Box<dyn Collidable>, opacity: Rc<dyn Texture>) -> Self {
+        Self {
+            collidable,
+            opacity,
+        }
+    }
+}
+
+impl Collidable for Cutout {
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        let mut rng = rand::thread_rng();
+        let mut t_min = t_min;
+        loop {
+            let collision = self.collidable.collision(ray, t_min, t_max)?;
+            let opacity = self.opacity.value(&collision).x;
+            if opacity >= 1.0 || (opacity > 0.0 && rng.gen::<f64>() < opacity) {
+                return Some(collision);
+            }
+            // Continue past the transparent collision.
+            t_min = collision.t;
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.collidable.bounding_box()
+    }
+}
 //
 // pub struct Cube {
 //     center: Vec3,
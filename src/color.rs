@@ -3,6 +3,7 @@ use std::fmt::{Display, Formatter};
 
 use crate::vector::Vec3;
 
+#[derive(Copy, Clone, Debug)]
 pub struct Color {
     pub r: u32,
     pub g: u32,
@@ -12,11 +13,12 @@ pub struct Color {
 impl Color {
     pub const BLACK: Color = Color { r: 0, g: 0, b: 0 };
 
+    /// Construct a color from components in [0.0, 1.0] (values outside are clamped).
     pub fn new(r: f64, g: f64, b: f64) -> Self {
         Self {
-            r: (255.999 * r) as u32,
-            g: (255.999 * g) as u32,
-            b: (255.999 * b) as u32,
+            r: (255.999 * r.clamp(0.0, 1.0)) as u32,
+            g: (255.999 * g.clamp(0.0, 1.0)) as u32,
+            b: (255.999 * b.clamp(0.0, 1.0)) as u32,
         }
     }
 }
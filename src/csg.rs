@@ -0,0 +1,132 @@
+//! Constructive solid geometry on closed collidables.
+use crate::aabb::Aabb;
+use crate::collide::Interval;
+use crate::{Collidable, Collision, Ray};
+
+/// Boolean operation combining two volumes.
+#[derive(Copy, Clone, Debug)]
+pub enum CsgOperation {
+    /// Points inside either volume.
+    Union,
+    /// Points inside both volumes.
+    Intersection,
+    /// Points inside the first volume but not the second.
+    Difference,
+}
+
+impl CsgOperation {
+    /// Whether a point is inside the result given whether it is inside either operand.
+    fn contains(self, a: bool, b: bool) -> bool {
+        match self {
+            CsgOperation::Union => a || b,
+            CsgOperation::Intersection => a && b,
+            CsgOperation::Difference => a && !b,
+        }
+    }
+}
+
+/// Collidable boolean combination of two closed collidables.
+///
+/// Both operands must enclose a volume (have `intervals`), otherwise nothing is collided. Surfaces
+/// of the second operand bounding a difference have their normals flipped to face out of the
+/// result.
+pub struct Csg {
+    operation: CsgOperation,
+    a: Box<dyn Collidable>,
+    b: Box<dyn Collidable>,
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, a: Box<dyn Collidable>, b: Box<dyn Collidable>) -> Self {
+        Self { operation, a, b }
+    }
+}
+
+/// Boundary of an operand volume along a ray.
+struct Event {
+    collision: Collision,
+    from_a: bool,
+    entering: bool,
+}
+
+impl Collidable for Csg {
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        self.intervals(ray)?
+            .into_iter()
+            .flat_map(|interval| vec![interval.entry, interval.exit])
+            .find(|collision| collision.t > t_min && collision.t < t_max)
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval>> {
+        let a = self.a.intervals(ray)?;
+        let b = self.b.intervals(ray)?;
+        let events = |intervals: Vec<Interval>, from_a: bool| {
+            intervals.into_iter().flat_map(move |interval| {
+                vec![
+                    Event {
+                        collision: interval.entry,
+                        from_a,
+                        entering: true,
+                    },
+                    Event {
+                        collision: interval.exit,
+                        from_a,
+                        entering: false,
+                    },
+                ]
+            })
+        };
+        let mut events: Vec<Event> = events(a, true).chain(events(b, false)).collect();
+        events.sort_by(|x, y| {
+            x.collision
+                .t
+                .partial_cmp(&y.collision.t)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        // Sweep along the ray, keeping the boundaries where the result changes.
+        let (mut inside_a, mut inside_b) = (false, false);
+        let mut inside = false;
+        let mut entry = None;
+        let mut intervals = Vec::new();
+        for event in events {
+            if event.from_a {
+                inside_a = event.entering;
+            } else {
+                inside_b = event.entering;
+            }
+            let now_inside = self.operation.contains(inside_a, inside_b);
+            if now_inside == inside {
+                continue;
+            }
+            inside = now_inside;
+
+            // A boundary entering the result while leaving an operand (or vice versa) faces the
+            // other way.
+            let mut collision = event.collision;
+            if event.entering != inside {
+                collision.front_face = !collision.front_face;
+            }
+            if inside {
+                entry = Some(collision);
+            } else if let Some(entry) = entry.take() {
+                intervals.push(Interval {
+                    entry,
+                    exit: collision,
+                });
+            }
+        }
+        Some(intervals)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self.operation {
+            CsgOperation::Union => Some(self.a.bounding_box()?.union(&self.b.bounding_box()?)),
+            CsgOperation::Intersection => match (self.a.bounding_box(), self.b.bounding_box()) {
+                (Some(a), Some(b)) => Some(Aabb::new(a.min.max(&b.min), a.max.min(&b.max))),
+                (a, b) => a.or(b),
+            },
+            CsgOperation::Difference => self.a.bounding_box(),
+        }
+    }
+}
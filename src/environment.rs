@@ -0,0 +1,185 @@
+//! Environment lighting for rays which escape the world.
+use std::f64::consts::PI;
+use std::path::Path;
+
+use image::ImageResult;
+use rand::Rng;
+
+use crate::sampling::Distribution2D;
+use crate::Vec3;
+
+/// Light arriving from infinitely far away, seen by rays which do not collide with anything.
+pub trait Environment {
+    /// Radiance arriving along the reverse of some ray direction.
+    fn color(&self, direction: &Vec3) -> Vec3;
+
+    /// Sample a direction towards the environment, returning it with its solid angle density.
+    ///
+    /// Environments which cannot be sampled return `None` and are only found by scattered rays.
+    fn sample(&self) -> Option<(Vec3, f64)> {
+        None
+    }
+
+    /// Solid angle density with which `sample` produces some direction.
+    fn pdf(&self, _direction: &Vec3) -> f64 {
+        0.0
+    }
+}
+
+/// Linear vertical gradient from white straight down to a sky color straight up.
+pub struct Gradient {
+    sky: Vec3,
+}
+
+impl Gradient {
+    pub fn new(sky: Vec3) -> Self {
+        Self { sky }
+    }
+}
+
+impl Environment for Gradient {
+    fn color(&self, direction: &Vec3) -> Vec3 {
+        let t = (direction.normalized().y + 1.0) * 0.5;
+        Vec3::one() * (1.0 - t) + self.sky * t
+    }
+}
+
+/// Latitude-longitude (equirectangular) environment map.
+///
+/// The image center looks along -Z, with the top row straight up (+Y). Directions are importance
+/// sampled proportional to pixel luminance, and black maps are not sampled.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+    rotation: f64,
+    intensity: f64,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    /// Construct an environment map from row-major pixels (top row first).
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> Self {
+        // Weighting rows by sin(θ) accounts for the compression of pixels towards the poles.
+        let luminance: Vec<f64> = pixels
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let theta = PI * ((i / width) as f64 + 0.5) / height as f64;
+                p.luminance() * theta.sin()
+            })
+            .collect();
+        let distribution = Distribution2D::new(&luminance, width, height);
+        Self {
+            width,
+            height,
+            pixels,
+            rotation: 0.0,
+            intensity: 1.0,
+            distribution,
+        }
+    }
+
+    /// Load an environment map from an image file (e.g. Radiance `.hdr`).
+    pub fn open<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        let image = image::open(path)?.into_rgb32f();
+        let (width, height) = image.dimensions();
+        let pixels = image
+            .pixels()
+            .map(|p| Vec3::new(p.0[0] as f64, p.0[1] as f64, p.0[2] as f64))
+            .collect();
+        Ok(Self::new(width as usize, height as usize, pixels))
+    }
+
+    /// Set the rotation of the map about the vertical axis in degrees.
+    pub fn set_rotation(&mut self, rotation: f64) -> &mut Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Set the multiplier applied to map radiance.
+    pub fn set_intensity(&mut self, intensity: f64) -> &mut Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Map coordinates in [0.0, 1.0)² of a direction.
+    fn direction_to_uv(&self, direction: &Vec3) -> (f64, f64) {
+        let d = direction.normalized();
+        let phi = d.x.atan2(-d.z) - self.rotation.to_radians();
+        let theta = d.y.clamp(-1.0, 1.0).acos();
+        ((phi / (2.0 * PI) + 0.5).rem_euclid(1.0), theta / PI)
+    }
+
+    /// Unit direction of map coordinates.
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let phi = (u - 0.5) * 2.0 * PI + self.rotation.to_radians();
+        let theta = v * PI;
+        Vec3::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        )
+    }
+
+    /// Convert a density over map coordinates to a solid angle density.
+    fn solid_angle_pdf(uv_pdf: f64, v: f64) -> f64 {
+        let sin_theta = (v * PI).sin();
+        if sin_theta > 0.0 {
+            uv_pdf / (2.0 * PI * PI * sin_theta)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn color(&self, direction: &Vec3) -> Vec3 {
+        let (u, v) = self.direction_to_uv(direction);
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.pixels[j * self.width + i] * self.intensity
+    }
+
+    fn sample(&self) -> Option<(Vec3, f64)> {
+        if self.distribution.integral() <= 0.0 {
+            return None;
+        }
+        let mut rng = rand::thread_rng();
+        let ((u, v), uv_pdf) = self.distribution.sample(rng.gen(), rng.gen());
+        let pdf = Self::solid_angle_pdf(uv_pdf, v);
+        if pdf > 0.0 {
+            Some((self.uv_to_direction(u, v), pdf))
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, direction: &Vec3) -> f64 {
+        if self.distribution.integral() <= 0.0 {
+            return 0.0;
+        }
+        let (u, v) = self.direction_to_uv(direction);
+        Self::solid_angle_pdf(self.distribution.pdf(u, v), v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn black_map_is_not_sampled() {
+        let map = EnvironmentMap::new(2, 1, vec![Vec3::zero(); 2]);
+        assert!(map.sample().is_none());
+        assert_eq!(map.pdf(&Vec3::new(0.0, 0.0, -1.0)), 0.0);
+    }
+
+    #[test]
+    fn sample_matches_pdf() {
+        let map = EnvironmentMap::new(1, 1, vec![Vec3::one()]);
+        let (direction, pdf) = map.sample().unwrap();
+        assert!((direction.magnitude() - 1.0).abs() < 1e-12);
+        assert!((pdf - map.pdf(&direction)).abs() < 1e-12);
+    }
+}
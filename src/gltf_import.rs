@@ -0,0 +1,403 @@
+//! glTF 2.0 (`.gltf` and `.glb`) scene importer.
+//!
+//! Each mesh is built once into a hierarchy in its own space and placed by an `Instance` at every
+//! node using it, cameras are placed at their nodes and metallic-roughness materials are mapped
+//! onto `Principled` materials.
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+use gltf::camera::Projection;
+use gltf::image::Format;
+
+use crate::bvh::Bvh;
+use crate::camera::{OrthographicCamera, PerspectiveCamera};
+use crate::instance::Instance;
+use crate::mesh::Mesh;
+use crate::principled::Principled;
+use crate::shading::NormalMap;
+use crate::texture::{srgb_to_linear, ChannelTexture, ImageTexture, ProductTexture, VertexColor};
+use crate::transform::Transform;
+use crate::{Camera, Collidable, Material, Texture, Vec3};
+
+/// Collidables and cameras imported from a glTF file.
+pub struct GltfScene {
+    /// Hierarchy over one instance per node with a mesh, sharing the hierarchy of the mesh.
+    pub bvh: Bvh,
+
+    /// Cameras in the order their nodes are visited.
+    pub cameras: Vec<Box<dyn Camera>>,
+}
+
+/// Load the default (or first) scene of a glTF file, along with any external buffers and images.
+pub fn open<P: AsRef<Path>>(path: P) -> gltf::Result<GltfScene> {
+    let (document, buffers, images) = gltf::import(path)?;
+    let mut importer = Importer {
+        buffers,
+        images,
+        meshes: HashMap::new(),
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        collidables: Vec::new(),
+        cameras: Vec::new(),
+    };
+    if let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        for node in scene.nodes() {
+            importer.node(&node, &Transform::identity());
+        }
+    }
+    Ok(GltfScene {
+        bvh: Bvh::new(importer.collidables),
+        cameras: importer.cameras,
+    })
+}
+
+/// Convert glTF image data to a texture of linear values.
+fn image_texture(data: &gltf::image::Data, srgb: bool) -> ImageTexture {
+    let (channels, bytes) = match data.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let channel = |bytes_at: &[u8]| match bytes {
+        1 => bytes_at[0] as f64 / 255.0,
+        2 => u16::from_le_bytes([bytes_at[0], bytes_at[1]]) as f64 / 65535.0,
+        _ => f32::from_le_bytes([bytes_at[0], bytes_at[1], bytes_at[2], bytes_at[3]]) as f64,
+    };
+    let pixels = data
+        .pixels
+        .chunks_exact(channels * bytes)
+        .map(|pixel| {
+            let c = |i: usize| channel(&pixel[bytes * i.min(channels - 1)..]);
+            // Single channel images are gray, and two channel images gray with alpha.
+            let color = if channels < 3 {
+                Vec3::new(c(0), c(0), c(0))
+            } else {
+                Vec3::new(c(0), c(1), c(2))
+            };
+            if srgb {
+                srgb_to_linear(&color)
+            } else {
+                color
+            }
+        })
+        .collect();
+    ImageTexture::new(data.width as usize, data.height as usize, pixels)
+}
+
+struct Importer {
+    buffers: Vec<gltf::buffer::Data>,
+    images: Vec<gltf::image::Data>,
+    /// Hierarchy over the triangle primitives of each mesh by mesh index, if it has any.
+    meshes: HashMap<usize, Option<Rc<dyn Collidable>>>,
+    /// Textures by image index and whether they hold sRGB colors.
+    textures: HashMap<(usize, bool), Rc<dyn Texture>>,
+    /// Materials by material index (`None` for the default material) and whether they use
+    /// vertex colors.
+    materials: HashMap<(Option<usize>, bool), Rc<dyn Material>>,
+    collidables: Vec<Box<dyn Collidable>>,
+    cameras: Vec<Box<dyn Camera>>,
+}
+
+impl Importer {
+    /// Import a node and its children.
+    ///
+    /// Nodes whose matrix is singular (e.g. scaled by zero) hide themselves and their children.
+    fn node(&mut self, node: &gltf::Node, parent: &Transform) {
+        // glTF matrices are column-major.
+        let columns = node.transform().matrix();
+        let mut matrix = [[0.0; 4]; 4];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = columns[j][i] as f64;
+            }
+        }
+        let transform = match Transform::from_matrix(matrix) {
+            Some(transform) => transform.then(parent),
+            None => return,
+        };
+
+        if let Some(object) = node.mesh().and_then(|mesh| self.mesh(&mesh)) {
+            self.collidables
+                .push(Box::new(Instance::new(object, transform)));
+        }
+        if let Some(camera) = node.camera() {
+            self.cameras.push(Self::camera(&camera, &transform));
+        }
+        for child in node.children() {
+            self.node(&child, &transform);
+        }
+    }
+
+    /// Camera looking down its node's -Z axis with +Y up.
+    fn camera(camera: &gltf::Camera, transform: &Transform) -> Box<dyn Camera> {
+        let origin = transform.point(&Vec3::zero());
+        let forward = transform.vector(&Vec3::new(0.0, 0.0, -1.0));
+        let up = transform.vector(&Vec3::new(0.0, 1.0, 0.0));
+        match camera.projection() {
+            Projection::Perspective(perspective) => {
+                let mut camera = PerspectiveCamera::new();
+                camera
+                    .look_from(origin)
+                    .look_at(origin + forward)
+                    .set_up(up)
+                    .set_vertical_fov((perspective.yfov() as f64).to_degrees())
+                    .set_aspect_ratio(perspective.aspect_ratio().unwrap_or(16.0 / 9.0) as f64)
+                    .set_focus_distance(1.0)
+                    .update();
+                Box::new(camera)
+            }
+            Projection::Orthographic(orthographic) => {
+                let mut camera = OrthographicCamera::new();
+                camera
+                    .look_from(origin)
+                    .look_at(origin + forward)
+                    .set_up(up)
+                    .set_view_height(2.0 * orthographic.ymag() as f64)
+                    .set_aspect_ratio((orthographic.xmag() / orthographic.ymag()) as f64)
+                    .update();
+                Box::new(camera)
+            }
+        }
+    }
+
+    /// Hierarchy over the triangles of all triangle primitives of a mesh, built on first use.
+    fn mesh(&mut self, mesh: &gltf::Mesh) -> Option<Rc<dyn Collidable>> {
+        if let Some(object) = self.meshes.get(&mesh.index()) {
+            return object.clone();
+        }
+        let triangles: Vec<Box<dyn Collidable>> = mesh
+            .primitives()
+            .filter_map(|primitive| self.primitive(&primitive))
+            .flat_map(|primitive| Rc::new(primitive).triangles())
+            .collect();
+        let object = if triangles.is_empty() {
+            None
+        } else {
+            Some(Rc::new(Bvh::new(triangles)) as Rc<dyn Collidable>)
+        };
+        self.meshes.insert(mesh.index(), object.clone());
+        object
+    }
+
+    /// Mesh of a triangle primitive, if it has valid triangles.
+    ///
+    /// Primitives with vertex indices out of range or attributes not given for every vertex are
+    /// skipped.
+    fn primitive(&mut self, primitive: &gltf::Primitive) -> Option<Mesh> {
+        if primitive.mode() != gltf::mesh::Mode::Triangles {
+            return None;
+        }
+        let buffers = &self.buffers;
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let to_vec3 = |v: [f32; 3]| Vec3::new(v[0] as f64, v[1] as f64, v[2] as f64);
+
+        let positions: Vec<Vec3> = reader.read_positions()?.map(to_vec3).collect();
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+        if indices.iter().any(|&i| i as usize >= positions.len()) {
+            return None;
+        }
+        let indices = indices
+            .chunks_exact(3)
+            .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+            .collect();
+        let normals: Option<Vec<Vec3>> = reader
+            .read_normals()
+            .map(|normals| normals.map(to_vec3).collect());
+        // glTF surface coordinates start from the top of images.
+        let uvs: Option<Vec<(f64, f64)>> = reader.read_tex_coords(0).map(|uvs| {
+            uvs.into_f32()
+                .map(|[u, v]| (u as f64, 1.0 - v as f64))
+                .collect()
+        });
+        let colors: Option<Vec<Vec3>> = reader
+            .read_colors(0)
+            .map(|colors| colors.into_rgb_f32().map(to_vec3).collect());
+        let vertices = positions.len();
+        if normals.as_ref().is_some_and(|n| n.len() != vertices)
+            || uvs.as_ref().is_some_and(|uv| uv.len() != vertices)
+            || colors.as_ref().is_some_and(|c| c.len() != vertices)
+        {
+            return None;
+        }
+
+        let material = self.material(&primitive.material(), colors.is_some());
+        let mut mesh = Mesh::new(positions, indices, material);
+        if let Some(normals) = normals {
+            mesh.set_normals(normals);
+        }
+        if let Some(uvs) = uvs {
+            mesh.set_uvs(uvs);
+        }
+        if let Some(colors) = colors {
+            mesh.set_colors(colors);
+        }
+        Some(mesh)
+    }
+
+    /// Texture of an image, converted once per color space.
+    fn texture(&mut self, texture: &gltf::Texture, srgb: bool) -> Rc<dyn Texture> {
+        let index = texture.source().index();
+        let images = &self.images;
+        self.textures
+            .entry((index, srgb))
+            .or_insert_with(|| Rc::new(image_texture(&images[index], srgb)))
+            .clone()
+    }
+
+    /// Principled material for a metallic-roughness material.
+    fn material(&mut self, material: &gltf::Material, vertex_colors: bool) -> Rc<dyn Material> {
+        let key = (material.index(), vertex_colors);
+        if let Some(material) = self.materials.get(&key) {
+            return material.clone();
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, _] = pbr.base_color_factor();
+        let mut base_color: Rc<dyn Texture> = Rc::new(Vec3::new(r as f64, g as f64, b as f64));
+        if let Some(info) = pbr.base_color_texture() {
+            let texture = self.texture(&info.texture(), true);
+            base_color = Rc::new(ProductTexture::new(texture, base_color));
+        }
+        if vertex_colors {
+            base_color = Rc::new(ProductTexture::new(Rc::new(VertexColor), base_color));
+        }
+
+        // Roughness and metalness are packed in the green and blue channels.
+        let mut metallic: Rc<dyn Texture> = Rc::new(pbr.metallic_factor() as f64);
+        let mut roughness: Rc<dyn Texture> = Rc::new(pbr.roughness_factor() as f64);
+        if let Some(info) = pbr.metallic_roughness_texture() {
+            let texture = self.texture(&info.texture(), false);
+            metallic = Rc::new(ProductTexture::new(
+                Rc::new(ChannelTexture::new(texture.clone(), 2)),
+                metallic,
+            ));
+            roughness = Rc::new(ProductTexture::new(
+                Rc::new(ChannelTexture::new(texture, 1)),
+                roughness,
+            ));
+        }
+
+        let mut principled = Principled::new(base_color);
+        principled.set_metallic(metallic).set_roughness(roughness);
+        let mut result: Rc<dyn Material> = Rc::new(principled);
+        if let Some(info) = material.normal_texture() {
+            let texture = self.texture(&info.texture(), false);
+            let mut normal_map = NormalMap::new(result, texture);
+            normal_map.set_strength(info.scale() as f64);
+            result = Rc::new(normal_map);
+        }
+        self.materials.insert(key, result.clone());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ray;
+
+    /// Binary glTF file of a single triangle, with the given JSON for meshes and nodes.
+    ///
+    /// Accessor 0 holds the three positions, 1 the valid indices `[0, 1, 2]`, 2 the indices
+    /// `[0, 1, 5]` out of range and 3 only two normals.
+    fn glb(name: &str, meshes_and_nodes: &str) -> std::path::PathBuf {
+        let mut binary = Vec::new();
+        for value in &[0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            binary.extend_from_slice(&value.to_le_bytes());
+        }
+        for index in &[0u16, 1, 2, 0, 0, 1, 5, 0] {
+            binary.extend_from_slice(&index.to_le_bytes());
+        }
+        let mut json = format!(
+            r#"{{
+                "asset": {{"version": "2.0"}},
+                "buffers": [{{"byteLength": {}}}],
+                "bufferViews": [
+                    {{"buffer": 0, "byteOffset": 0, "byteLength": 36}},
+                    {{"buffer": 0, "byteOffset": 36, "byteLength": 6}},
+                    {{"buffer": 0, "byteOffset": 44, "byteLength": 6}}
+                ],
+                "accessors": [
+                    {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                      "min": [0, 0, 0], "max": [1, 1, 0]}},
+                    {{"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}},
+                    {{"bufferView": 2, "componentType": 5123, "count": 3, "type": "SCALAR"}},
+                    {{"bufferView": 0, "componentType": 5126, "count": 2, "type": "VEC3"}}
+                ],
+                {}
+            }}"#,
+            binary.len(),
+            meshes_and_nodes
+        )
+        .into_bytes();
+        json.resize(json.len().div_ceil(4) * 4, b' ');
+
+        let mut bytes = b"glTF".to_vec();
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        bytes.extend_from_slice(&((12 + 8 + json.len() + 8 + binary.len()) as u32).to_le_bytes());
+        bytes.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(b"JSON");
+        bytes.extend_from_slice(&json);
+        bytes.extend_from_slice(&(binary.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(b"BIN\0");
+        bytes.extend_from_slice(&binary);
+
+        let path = std::env::temp_dir().join(format!("ray-tracing-{}.glb", name));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    /// Distance to the closest triangle hit by a ray going down the z axis from `z = 5`.
+    fn distance(scene: &GltfScene) -> Option<f64> {
+        let ray = Ray::new(Vec3::new(0.25, 0.25, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        scene
+            .bvh
+            .collision(&ray, 1e-6, f64::INFINITY)
+            .map(|collision| collision.t)
+    }
+
+    #[test]
+    fn singular_nodes_hide_children() {
+        let path = glb(
+            "singular",
+            r#""meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]}],
+               "nodes": [
+                   {"mesh": 0},
+                   {"mesh": 0, "scale": [0, 0, 0], "children": [2]},
+                   {"mesh": 0, "translation": [0, 0, 1]}
+               ],
+               "scenes": [{"nodes": [0, 1]}]"#,
+        );
+        // The child raised to `z = 1` is hidden along with its parent.
+        assert_eq!(distance(&open(path).unwrap()), Some(5.0));
+    }
+
+    #[test]
+    fn invalid_primitives_are_skipped() {
+        let path = glb(
+            "invalid",
+            r#""meshes": [{"primitives": [
+                   {"attributes": {"POSITION": 0}, "indices": 1},
+                   {"attributes": {"POSITION": 0}, "indices": 2},
+                   {"attributes": {"POSITION": 0, "NORMAL": 3}, "indices": 1}
+               ]}],
+               "nodes": [{"mesh": 0}],
+               "scenes": [{"nodes": [0]}]"#,
+        );
+        assert_eq!(distance(&open(path).unwrap()), Some(5.0));
+    }
+}
@@ -0,0 +1,265 @@
+//! Instanced geometry sharing a single collidable between many placements.
+//!
+//! Instances of a shared bottom-level `Bvh` can themselves be gathered into a top-level `Bvh`,
+//! forming a two-level acceleration structure whose memory grows with the number of instances
+//! rather than with the total amount of instanced geometry.
+use std::rc::Rc;
+
+use crate::aabb::Aabb;
+use crate::collide::Interval;
+use crate::transform::Transform;
+use crate::{Collidable, Collision, Ray, Vec3};
+
+/// Collidable placed into the world by a transformation.
+pub struct Instance {
+    object: Rc<dyn Collidable>,
+    transform: Transform,
+}
+
+impl Instance {
+    /// Construct an instance.
+    ///
+    /// * `object` - Shared collidable in its own (object) space.
+    /// * `transform` - Transformation from object space into the world.
+    pub fn new(object: Rc<dyn Collidable>, transform: Transform) -> Self {
+        Self { object, transform }
+    }
+
+    /// Ray in object space.
+    ///
+    /// The direction is left unnormalized so distances along the ray are the same in both spaces.
+    fn to_object(&self, ray: &Ray) -> Ray {
+        let inverse = self.transform.inverse();
+        Ray::new(inverse.point(&ray.point), inverse.vector(&ray.direction))
+    }
+
+    /// Collision in world space of a collision in object space.
+    fn to_world(&self, ray: &Ray, mut collision: Collision) -> Collision {
+        // The inverse transpose preserves the sign of the normal against the ray, so normals keep
+        // facing the ray and `front_face` is unchanged.
+        let normal = self.transform.normal(&collision.normal).normalized();
+        let tangent = self.transform.vector(&collision.tangent);
+        let tangent = tangent - normal * tangent.dot(&normal);
+        collision.point = ray.at(collision.t);
+        collision.normal = normal;
+        collision.geometric_normal = self
+            .transform
+            .normal(&collision.geometric_normal)
+            .normalized();
+        collision.tangent = if tangent.magnitude_squared() > 1e-12 {
+            tangent.normalized()
+        } else {
+            normal.orthonormal_basis().0
+        };
+        collision
+    }
+}
+
+impl Collidable for Instance {
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        self.object
+            .collision(&self.to_object(ray), t_min, t_max)
+            .map(|collision| self.to_world(ray, collision))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bounds = self.object.bounding_box()?;
+        let corners: Vec<Vec3> = (0..8)
+            .map(|i| {
+                let corner = |axis: usize| {
+                    if i & (1 << axis) == 0 {
+                        bounds.min[axis]
+                    } else {
+                        bounds.max[axis]
+                    }
+                };
+                self.transform
+                    .point(&Vec3::new(corner(0), corner(1), corner(2)))
+            })
+            .collect();
+        Aabb::from_points(&corners)
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval>> {
+        let intervals = self.object.intervals(&self.to_object(ray))?;
+        Some(
+            intervals
+                .into_iter()
+                .map(|interval| Interval {
+                    entry: self.to_world(ray, interval.entry),
+                    exit: self.to_world(ray, interval.exit),
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collide::Sphere;
+    use crate::material::Lambert;
+    use crate::mesh::Mesh;
+    use crate::Material;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).magnitude() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    fn material() -> Rc<dyn Material> {
+        Rc::new(Lambert::new(Vec3::new(0.5, 0.5, 0.5)))
+    }
+
+    /// Rotation after a non-uniform scale, followed by a translation.
+    fn transform() -> Transform {
+        Transform::scale(Vec3::new(2.0, 0.5, 1.5))
+            .then(&Transform::rotate(30.0, Vec3::new(1.0, 1.0, 0.0)))
+            .then(&Transform::translate(Vec3::new(1.0, -2.0, 3.0)))
+    }
+
+    /// Unit cube centered at the origin, with triangles wound about their outward normals.
+    ///
+    /// The cube is turned so that its faces are not aligned with the axes of the scale.
+    fn cube() -> Mesh {
+        let positions: Vec<Vec3> = (0..8)
+            .map(|i| {
+                let corner = |axis: usize| ((i >> axis) & 1) as f64 - 0.5;
+                Vec3::new(corner(0), corner(1), corner(2))
+            })
+            .collect();
+        let mut indices = Vec::new();
+        for &[a, b, c, d] in &[
+            [0, 1, 3, 2],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 3, 7, 6],
+            [0, 2, 6, 4],
+            [1, 3, 7, 5],
+        ] {
+            for &[i, j, k] in &[[a, b, c], [a, c, d]] {
+                let normal = (positions[j] - positions[i]).cross(&(positions[k] - positions[i]));
+                if normal.dot(&positions[i]) > 0.0 {
+                    indices.push([i, j, k]);
+                } else {
+                    indices.push([i, k, j]);
+                }
+            }
+        }
+        let mut mesh = Mesh::new(positions, indices, material());
+        mesh.transform(&Transform::rotate(40.0, Vec3::new(1.0, 2.0, 3.0)));
+        mesh
+    }
+
+    /// Random rays around the transformed origin, starting both inside and outside.
+    fn rays(count: usize) -> Vec<Ray> {
+        let mut rng = StdRng::seed_from_u64(5);
+        let center = transform().point(&Vec3::zero());
+        let mut random = |scale: f64| {
+            Vec3::new(
+                rng.gen_range(-scale, scale),
+                rng.gen_range(-scale, scale),
+                rng.gen_range(-scale, scale),
+            )
+        };
+        (0..count)
+            .map(|_| {
+                let point = center + random(4.0);
+                let target = center + random(1.0);
+                Ray::new(point, target - point)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn collision_matches_world_space_mesh() {
+        let instance = Instance::new(Rc::new(cube().into_bvh()), transform());
+        let mut world = cube();
+        world.transform(&transform());
+        let world = world.into_bvh();
+
+        let mut hits = 0;
+        for ray in rays(1000) {
+            let expected = world.collision(&ray, 1e-6, f64::INFINITY);
+            let found = instance.collision(&ray, 1e-6, f64::INFINITY);
+            match (expected, found) {
+                (Some(expected), Some(found)) => {
+                    assert!((found.t - expected.t).abs() < 1e-9, "{:?}", ray);
+                    assert_close(found.point, expected.point);
+                    assert_close(found.normal, expected.normal);
+                    assert_close(found.geometric_normal, expected.geometric_normal);
+                    assert_eq!(found.front_face, expected.front_face);
+                    assert!(found.tangent.dot(&found.normal).abs() < 1e-9);
+                    hits += 1;
+                }
+                (None, None) => {}
+                (expected, found) => panic!(
+                    "{:?} hit at {:?} rather than {:?}",
+                    ray,
+                    found.map(|c| c.t),
+                    expected.map(|c| c.t)
+                ),
+            }
+        }
+        assert!(hits > 500, "only {} hits", hits);
+    }
+
+    #[test]
+    fn intervals_lie_on_transformed_surface() {
+        let center = Vec3::new(0.2, 0.0, -0.1);
+        let instance = Instance::new(Rc::new(Sphere::new(center, 1.0, material())), transform());
+        let inverse = transform().inverse();
+
+        let mut hits = 0;
+        for ray in rays(200) {
+            let intervals = instance.intervals(&ray).unwrap();
+            assert!(intervals.len() <= 1);
+            for interval in &intervals {
+                assert!(interval.entry.t < interval.exit.t);
+                assert!(interval.entry.front_face && !interval.exit.front_face);
+                for collision in &[&interval.entry, &interval.exit] {
+                    assert_close(collision.point, ray.at(collision.t));
+                    let local = inverse.point(&collision.point) - center;
+                    assert!((local.magnitude() - 1.0).abs() < 1e-9);
+                    let outward = transform().normal(&local).normalized();
+                    let facing = if collision.front_face {
+                        outward
+                    } else {
+                        -outward
+                    };
+                    assert_close(collision.normal, facing);
+                    assert_close(collision.geometric_normal, facing);
+                }
+                // The closest collision ahead of the ray is the entry or, from inside, the exit.
+                let closest = instance.collision(&ray, 0.0, f64::INFINITY).map(|c| c.t);
+                let expected = [interval.entry.t, interval.exit.t]
+                    .iter()
+                    .copied()
+                    .find(|&t| t > 0.0);
+                match (closest, expected) {
+                    (Some(closest), Some(expected)) => assert!((closest - expected).abs() < 1e-9),
+                    (closest, expected) => assert_eq!(closest, expected),
+                }
+                hits += 1;
+            }
+        }
+        assert!(hits > 100, "only {} hits", hits);
+    }
+
+    #[test]
+    fn bounding_box_contains_transformed_corners() {
+        let mesh = cube();
+        let bounds = Instance::new(Rc::new(cube().into_bvh()), transform())
+            .bounding_box()
+            .unwrap();
+        assert_eq!(mesh.positions().len(), 8);
+        for corner in mesh.positions() {
+            let corner = transform().point(corner);
+            for axis in 0..3 {
+                assert!(bounds.min[axis] - 1e-9 <= corner[axis]);
+                assert!(corner[axis] <= bounds.max[axis] + 1e-9);
+            }
+        }
+    }
+}
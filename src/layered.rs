@@ -0,0 +1,237 @@
+//! Materials combining other materials, by mixing or by layering.
+use std::rc::Rc;
+
+use rand::Rng;
+
+use crate::material::Material;
+use crate::microfacet::{
+    fresnel_dielectric, ggx_d, roughness_to_alpha, sample_visible_normal, smith_g1, smith_g2,
+    visible_normal_pdf, Frame,
+};
+use crate::{Collision, Ray, Texture, Vec3};
+
+/// Blend of two materials by a weight which may vary over the surface.
+///
+/// Rays are scattered by one of the two materials, chosen at random with probability given by the
+/// weight. Only the non-specular parts of the two materials are represented by `evaluate` and
+/// `pdf`, so a specular material may be mixed with a non-specular one.
+pub struct MixMaterial {
+    a: Rc<dyn Material>,
+    b: Rc<dyn Material>,
+    weight: Rc<dyn Texture>,
+}
+
+impl MixMaterial {
+    /// Construct a mix of two materials.
+    ///
+    /// * `weight` - Fraction of `b` in the mix, in [0.0, 1.0].
+    pub fn new(a: Rc<dyn Material>, b: Rc<dyn Material>, weight: Rc<dyn Texture>) -> Self {
+        Self { a, b, weight }
+    }
+
+    /// Fraction of `b` at a collision.
+    fn weight(&self, collision: &Collision) -> f64 {
+        self.weight.value(collision).x.clamp(0.0, 1.0)
+    }
+}
+
+impl Material for MixMaterial {
+    fn scatter(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3)> {
+        self.scatter_lobe(ray_in, collision)
+            .map(|(scattered, attenuation, _)| (scattered, attenuation))
+    }
+
+    fn scatter_lobe(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3, bool)> {
+        if rand::thread_rng().gen::<f64>() < self.weight(collision) {
+            self.b.scatter_lobe(ray_in, collision)
+        } else {
+            self.a.scatter_lobe(ray_in, collision)
+        }
+    }
+
+    /// Specular materials contribute nothing, leaving the weighted non-specular materials.
+    fn evaluate(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> Vec3 {
+        let weight = self.weight(collision);
+        self.a.evaluate(ray_in, collision, direction) * (1.0 - weight)
+            + self.b.evaluate(ray_in, collision, direction) * weight
+    }
+
+    /// Density of the non-specular materials, each weighted by the probability of choosing it.
+    fn pdf(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> f64 {
+        let weight = self.weight(collision);
+        self.a.pdf(ray_in, collision, direction) * (1.0 - weight)
+            + self.b.pdf(ray_in, collision, direction) * weight
+    }
+
+    fn is_specular(&self) -> bool {
+        self.a.is_specular() && self.b.is_specular()
+    }
+}
+
+/// Clear dielectric coating over a base material, such as varnish or lacquer.
+///
+/// Light is reflected by the coating with a GGX microfacet distribution, and the remainder
+/// reaches the base material attenuated by the Fresnel transmittance into and out of the coating.
+/// The coating is never specular, even over a specular base.
+pub struct CoatedMaterial {
+    base: Rc<dyn Material>,
+    refraction_index: f64,
+    alpha: f64,
+}
+
+impl CoatedMaterial {
+    /// Construct a coated material.
+    ///
+    /// * `base` - Material beneath the coating.
+    /// * `refraction_index` - Index of refraction of the coating.
+    /// * `roughness` - Perceptual roughness of the coating in [0.0, 1.0].
+    pub fn new(base: Rc<dyn Material>, refraction_index: f64, roughness: f64) -> Self {
+        Self {
+            base,
+            refraction_index,
+            alpha: roughness_to_alpha(roughness),
+        }
+    }
+
+    /// Fresnel transmittance through the coating along a local direction.
+    fn transmittance(&self, w: &Vec3) -> f64 {
+        1.0 - fresnel_dielectric(w.z.abs(), self.refraction_index)
+    }
+
+    /// Scattering function times cosine, and sampling density, of reflection by the coating.
+    fn evaluate_coating(&self, wo: &Vec3, wi: &Vec3) -> (f64, f64) {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let m = (*wo + *wi).normalized();
+        let fresnel = fresnel_dielectric(wo.dot(&m), self.refraction_index);
+        let value = fresnel * ggx_d(&m, self.alpha) * smith_g2(wo, wi, self.alpha) / (4.0 * wo.z);
+        let pdf = visible_normal_pdf(wo, &m, self.alpha) / (4.0 * wo.dot(&m));
+        (value, pdf)
+    }
+}
+
+impl Material for CoatedMaterial {
+    fn scatter(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3)> {
+        self.scatter_lobe(ray_in, collision)
+            .map(|(scattered, attenuation, _)| (scattered, attenuation))
+    }
+
+    fn scatter_lobe(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3, bool)> {
+        // Rays leaving the inside of a base object never meet the coating.
+        if !collision.front_face {
+            return self.base.scatter_lobe(ray_in, collision);
+        }
+        let mut rng = rand::thread_rng();
+        let frame = Frame::new(collision.normal);
+        let wo = frame.to_local(&-ray_in.direction.normalized());
+        // Reflect off the coating with the probability of macroscopic Fresnel reflection.
+        let coating_probability = 1.0 - self.transmittance(&wo);
+        if rng.gen::<f64>() < coating_probability {
+            let m = sample_visible_normal(&wo, self.alpha, rng.gen(), rng.gen());
+            let wi = (-wo).reflect(m);
+            if wi.z <= 0.0 {
+                return None;
+            }
+            let fresnel = fresnel_dielectric(wo.dot(&m), self.refraction_index);
+            let attenuation = Vec3::one()
+                * (fresnel * smith_g2(&wo, &wi, self.alpha)
+                    / (smith_g1(&wo, self.alpha) * coating_probability));
+            Some((
+                Ray::new(collision.point, frame.to_world(&wi)),
+                attenuation,
+                false,
+            ))
+        } else {
+            // The transmittance along `wo` cancels with the probability of choosing the base.
+            let (scattered, attenuation, specular) = self.base.scatter_lobe(ray_in, collision)?;
+            let wi = frame.to_local(&scattered.direction.normalized());
+            Some((scattered, attenuation * self.transmittance(&wi), specular))
+        }
+    }
+
+    /// A specular base contributes nothing, leaving the coating.
+    fn evaluate(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> Vec3 {
+        if !collision.front_face {
+            return self.base.evaluate(ray_in, collision, direction);
+        }
+        let frame = Frame::new(collision.normal);
+        let wo = frame.to_local(&-ray_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        let (coating, _) = self.evaluate_coating(&wo, &wi);
+        Vec3::one() * coating
+            + self.base.evaluate(ray_in, collision, direction)
+                * (self.transmittance(&wo) * self.transmittance(&wi))
+    }
+
+    fn pdf(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> f64 {
+        if !collision.front_face {
+            return self.base.pdf(ray_in, collision, direction);
+        }
+        let frame = Frame::new(collision.normal);
+        let wo = frame.to_local(&-ray_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        let (_, coating) = self.evaluate_coating(&wo, &wi);
+        let coating_probability = 1.0 - self.transmittance(&wo);
+        coating * coating_probability
+            + self.base.pdf(ray_in, collision, direction) * (1.0 - coating_probability)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collide::{Collidable, Sphere};
+    use crate::material::{Dielectric, Lambert, Metal};
+
+    /// Collision of a ray coming down onto the top of a unit sphere touching the origin.
+    fn collision(material: Rc<dyn Material>) -> (Ray, Collision) {
+        let ray = Ray::new(Vec3::new(0.3, 1.0, 0.0), Vec3::new(-0.3, -1.0, 0.0));
+        let sphere = Sphere::new(Vec3::new(0.0, -1.0, 0.0), 1.0, material);
+        let collision = sphere.collision(&ray, 1e-3, f64::INFINITY).unwrap();
+        (ray, collision)
+    }
+
+    #[test]
+    fn mix_with_specular_keeps_non_specular_part() {
+        let lambert: Rc<dyn Material> = Rc::new(Lambert::new(Vec3::new(0.5, 0.5, 0.5)));
+        let mirror: Rc<dyn Material> = Rc::new(Metal::new(Vec3::one(), 0.0));
+        let mix = MixMaterial::new(lambert.clone(), mirror.clone(), Rc::new(0.25));
+        assert!(!mix.is_specular());
+        assert!(MixMaterial::new(mirror.clone(), mirror, Rc::new(0.5)).is_specular());
+
+        let (ray, collision) = collision(Rc::new(mix));
+        let mix = collision.material.clone();
+        let direction = Vec3::new(0.2, 1.0, 0.1);
+        let expected = lambert.evaluate(&ray, &collision, &direction) * 0.75;
+        assert!((mix.evaluate(&ray, &collision, &direction) - expected).magnitude() < 1e-12);
+        let expected = lambert.pdf(&ray, &collision, &direction) * 0.75;
+        assert!((mix.pdf(&ray, &collision, &direction) - expected).abs() < 1e-12);
+
+        // Mirror reflections are reported as specular, diffuse ones are not.
+        for _ in 0..100 {
+            let (scattered, _, specular) = mix.scatter_lobe(&ray, &collision).unwrap();
+            let mirrored = Vec3::new(-0.3, 1.0, 0.0).normalized();
+            assert_eq!(
+                specular,
+                (scattered.direction.normalized() - mirrored).magnitude() < 1e-9
+            );
+        }
+    }
+
+    #[test]
+    fn coating_over_specular_base_is_not_specular() {
+        for base in [
+            Rc::new(Metal::new(Vec3::one(), 0.0)) as Rc<dyn Material>,
+            Rc::new(Dielectric::new(1.5)),
+        ] {
+            let coated = CoatedMaterial::new(base, 1.5, 0.3);
+            assert!(!coated.is_specular());
+            let (ray, collision) = collision(Rc::new(coated));
+            let direction = Vec3::new(-0.3, 1.0, 0.0);
+            let material = collision.material.clone();
+            assert!(material.evaluate(&ray, &collision, &direction).x > 0.0);
+            assert!(material.pdf(&ray, &collision, &direction) > 0.0);
+        }
+    }
+}
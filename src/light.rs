@@ -0,0 +1,316 @@
+//! Light sources which illuminate collisions directly through shadow rays.
+use std::rc::Rc;
+
+use rand::Rng;
+
+use crate::sampling::Distribution1D;
+use crate::{Collision, Material, Ray, Vec3};
+
+/// Light arriving at a point from a light source.
+pub struct LightSample {
+    /// Unit direction from the point towards the light.
+    pub direction: Vec3,
+    /// Distance from the point to the light (infinite for directional lights).
+    pub distance: f64,
+    /// Radiance arriving at the point, before occlusion.
+    pub radiance: Vec3,
+}
+
+/// A light source located at a single point or direction, which can only be found by sampling.
+pub trait Light {
+    /// Sample the light arriving at a point, if any.
+    fn sample(&self, point: &Vec3) -> Option<LightSample>;
+}
+
+/// Light emitted equally in all directions from a point, with inverse-square falloff.
+pub struct PointLight {
+    position: Vec3,
+    intensity: Vec3,
+}
+
+impl PointLight {
+    /// Construct a point light.
+    ///
+    /// * `position` - Point from which the light is emitted.
+    /// * `intensity` - Radiant intensity (arriving light at unit distance).
+    pub fn new(position: Vec3, intensity: Vec3) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, point: &Vec3) -> Option<LightSample> {
+        let offset = self.position - point;
+        let distance_squared = offset.magnitude_squared();
+        let distance = distance_squared.sqrt();
+        Some(LightSample {
+            direction: offset / distance,
+            distance,
+            radiance: self.intensity / distance_squared,
+        })
+    }
+}
+
+/// Point light emitting within a cone, with a smooth falloff towards the cone edge.
+pub struct SpotLight {
+    position: Vec3,
+    direction: Vec3,
+    intensity: Vec3,
+    cos_cone: f64,
+    cos_falloff_start: f64,
+}
+
+impl SpotLight {
+    /// Construct a spot light.
+    ///
+    /// * `position` - Point from which the light is emitted.
+    /// * `direction` - Direction of the cone axis.
+    /// * `intensity` - Radiant intensity along the cone axis.
+    /// * `cone_angle` - Angle between the cone axis and edge in degrees.
+    /// * `falloff_angle` - Angle over which intensity falls to zero at the cone edge in degrees.
+    pub fn new(
+        position: Vec3,
+        direction: Vec3,
+        intensity: Vec3,
+        cone_angle: f64,
+        falloff_angle: f64,
+    ) -> Self {
+        Self {
+            position,
+            direction: direction.normalized(),
+            intensity,
+            cos_cone: cone_angle.to_radians().cos(),
+            cos_falloff_start: (cone_angle - falloff_angle).max(0.0).to_radians().cos(),
+        }
+    }
+
+    /// Fraction of the axial intensity emitted at some cosine from the cone axis.
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta < self.cos_cone {
+            0.0
+        } else if cos_theta >= self.cos_falloff_start {
+            1.0
+        } else {
+            let t = (cos_theta - self.cos_cone) / (self.cos_falloff_start - self.cos_cone);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, point: &Vec3) -> Option<LightSample> {
+        let offset = self.position - point;
+        let distance_squared = offset.magnitude_squared();
+        let distance = distance_squared.sqrt();
+        let direction = offset / distance;
+        let falloff = self.falloff((-direction).dot(&self.direction));
+        if falloff <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.intensity * (falloff / distance_squared),
+        })
+    }
+}
+
+/// Light arriving from a single direction infinitely far away (e.g. sunlight).
+pub struct DirectionalLight {
+    direction: Vec3,
+    radiance: Vec3,
+}
+
+impl DirectionalLight {
+    /// Construct a directional light.
+    ///
+    /// * `direction` - Direction in which the light travels.
+    /// * `radiance` - Irradiance arriving on surfaces facing the light.
+    pub fn new(direction: Vec3, radiance: Vec3) -> Self {
+        Self {
+            direction: direction.normalized(),
+            radiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _point: &Vec3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction,
+            distance: f64::INFINITY,
+            radiance: self.radiance,
+        })
+    }
+}
+
+/// Surface over which points can be sampled uniformly, so that it can emit as an area light.
+pub trait Surface {
+    /// Area of the surface.
+    fn area(&self) -> f64;
+
+    /// Map two uniform random numbers to a point uniformly distributed over the surface, returning
+    /// it with the unit outward normal there.
+    fn sample_point(&self, u0: f64, u1: f64) -> (Vec3, Vec3);
+}
+
+/// Light emitted by surfaces with an emissive material, sampled uniformly by area.
+///
+/// The surfaces must also be collidables of the world holding the same material, by which
+/// collisions with the light are recognized. Their emission is then weighted by multiple
+/// importance sampling against scattered rays which find it.
+pub struct AreaLight {
+    surfaces: Vec<Box<dyn Surface>>,
+    /// Distribution of surfaces by area.
+    distribution: Distribution1D,
+    area: f64,
+    material: Rc<dyn Material>,
+}
+
+impl AreaLight {
+    /// Construct an area light.
+    ///
+    /// * `surfaces` - Surfaces emitting the light.
+    /// * `material` - Emissive material shared by the surfaces.
+    pub fn new(surfaces: Vec<Box<dyn Surface>>, material: Rc<dyn Material>) -> Self {
+        let areas: Vec<f64> = surfaces.iter().map(|s| s.area()).collect();
+        let area = areas.iter().sum();
+        Self {
+            surfaces,
+            distribution: Distribution1D::new(areas),
+            area,
+            material,
+        }
+    }
+
+    /// Solid angle density of a point on the light seen from another point.
+    ///
+    /// * `offset` - Offset from the other point to the point on the light.
+    /// * `normal` - Normal of the light at the point.
+    fn solid_angle_pdf(&self, offset: &Vec3, normal: &Vec3) -> f64 {
+        let distance_squared = offset.magnitude_squared();
+        let cos_theta = offset.dot(normal).abs() / distance_squared.sqrt();
+        if cos_theta > 0.0 && self.area > 0.0 {
+            distance_squared / (cos_theta * self.area)
+        } else {
+            0.0
+        }
+    }
+
+    /// Sample the light arriving at a point, returning it with its solid angle density.
+    pub fn sample(&self, point: &Vec3) -> Option<(LightSample, f64)> {
+        if self.area <= 0.0 {
+            return None;
+        }
+        let mut rng = rand::thread_rng();
+        let (_, _, index) = self.distribution.sample(rng.gen());
+        let (position, normal) = self.surfaces[index].sample_point(rng.gen(), rng.gen());
+
+        let offset = position - point;
+        let pdf = self.solid_angle_pdf(&offset, &normal);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let distance = offset.magnitude();
+        let direction = offset / distance;
+        // Emission towards the point, from the front face unless the material is two sided.
+        let ray = Ray::new(*point, direction);
+        let collision = Collision::new(
+            &ray,
+            distance,
+            position,
+            normal,
+            (0.0, 0.0),
+            Vec3::zero(),
+            self.material.clone(),
+        );
+        let radiance = self.material.emitted(&ray, &collision);
+        Some((
+            LightSample {
+                direction,
+                distance,
+                radiance,
+            },
+            pdf,
+        ))
+    }
+
+    /// Solid angle density with which `sample` produces a collision seen from a point, zero if
+    /// the collision is not with this light.
+    pub fn pdf(&self, point: &Vec3, collision: &Collision) -> f64 {
+        let same_material =
+            std::ptr::addr_eq(Rc::as_ptr(&self.material), Rc::as_ptr(&collision.material));
+        if same_material {
+            self.solid_angle_pdf(&(collision.point - point), &collision.geometric_normal)
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collide::{Disk, Sphere};
+    use crate::material::{Emissive, Lambert};
+    use crate::Collidable;
+
+    fn emissive() -> Rc<dyn Material> {
+        let lambert = Rc::new(Lambert::new(Vec3::new(0.5, 0.5, 0.5)));
+        Rc::new(Emissive::new(lambert, Vec3::one()))
+    }
+
+    /// Samples of a light whose pdf matches the density of the collision found towards them.
+    fn assert_samples_match_pdf(light: &AreaLight, collidable: &dyn Collidable, point: Vec3) {
+        let mut found = 0;
+        for _ in 0..100 {
+            if let Some((sample, pdf)) = light.sample(&point) {
+                let ray = Ray::new(point, sample.direction);
+                let collision = collidable.collision(&ray, 1e-6, f64::INFINITY).unwrap();
+                if (collision.t - sample.distance).abs() < 1e-9 {
+                    found += 1;
+                    assert!((light.pdf(&point, &collision) - pdf).abs() < 1e-9 * pdf);
+                }
+            }
+        }
+        assert!(found > 0);
+    }
+
+    #[test]
+    fn sphere_light_pdf() {
+        let material = emissive();
+        let sphere = Sphere::new(Vec3::new(0.0, 2.0, 0.0), 0.5, material.clone());
+        let light = AreaLight::new(vec![Box::new(sphere.clone())], material);
+        assert_samples_match_pdf(&light, &sphere, Vec3::zero());
+    }
+
+    #[test]
+    fn disk_light_pdf() {
+        let material = emissive();
+        let disk = Disk::new(
+            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.2),
+            0.5,
+            material.clone(),
+        );
+        let light = AreaLight::new(vec![Box::new(disk.clone())], material);
+        assert_samples_match_pdf(&light, &disk, Vec3::zero());
+
+        // Only the front face emits.
+        let (sample, _) = light.sample(&Vec3::new(0.0, 4.0, 0.0)).unwrap();
+        assert_eq!(sample.radiance.magnitude(), 0.0);
+    }
+
+    #[test]
+    fn pdf_of_other_material_is_zero() {
+        let material = emissive();
+        let sphere = Sphere::new(Vec3::new(0.0, 2.0, 0.0), 0.5, emissive());
+        let light = AreaLight::new(vec![Box::new(sphere.clone())], material);
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 1.0, 0.0));
+        let collision = sphere.collision(&ray, 1e-6, f64::INFINITY).unwrap();
+        assert_eq!(light.pdf(&Vec3::zero(), &collision), 0.0);
+    }
+}
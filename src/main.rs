@@ -1,4 +1,3 @@
-#![feature(trait_alias, const_int_pow)]
 // #![allow(unused_imports)]
 // #![allow(unused_variables)]
 // #![allow(unused_mut)]
@@ -6,25 +5,51 @@
 // #![allow(unused_doc_comments)]
 // #![allow(unused_assignments)]
 
-use std::fs::File;
-use std::io::{Result, Write as IoWrite};
+use std::io::Result;
 use std::rc::Rc;
 
-use itertools::iproduct;
-
+pub mod aabb;
+pub mod aperture;
+pub mod bvh;
 pub mod camera;
 pub mod collide;
 pub mod color;
+pub mod csg;
+pub mod environment;
+pub mod gltf_import;
+pub mod instance;
+pub mod layered;
+pub mod light;
 pub mod material;
+pub mod mesh;
+pub mod microfacet;
+pub mod pbrt;
+pub mod ply;
+pub mod polynomial;
+pub mod principled;
+pub mod quadric;
 pub mod ray;
+pub mod render;
+pub mod sampling;
+pub mod sdf;
+pub mod shading;
+pub mod sky;
+pub mod stl;
+pub mod texture;
+pub mod torus;
+pub mod transform;
 pub mod vector;
 pub mod world;
 
-use camera::Camera;
+use camera::{Camera, PerspectiveCamera};
 use collide::{Collidable, Collision, FindCollision, Sphere};
 use color::Color;
+use environment::{Environment, Gradient};
+use light::Light;
 use material::*;
 use ray::Ray;
+use render::Renderer;
+use texture::Texture;
 use vector::Vec3;
 use world::World;
 
@@ -43,7 +68,6 @@ fn main() -> Result<()> {
         // 900
     };
     const IMAGE_HEIGHT: usize = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as usize;
-    const IMAGE_AREA: usize = IMAGE_WIDTH * IMAGE_HEIGHT;
 
     let mut rng = rand::thread_rng();
 
@@ -85,7 +109,15 @@ fn main() -> Result<()> {
         )),
     ];
 
-    let world = World::new(SKY, collidables, T_MIN, T_MAX, MAX_DEPTH, SAMPLES_PER_PIXEL);
+    let world = World::new(
+        Box::new(Gradient::new(SKY)),
+        collidables,
+        vec![],
+        T_MIN,
+        T_MAX,
+        MAX_DEPTH,
+        SAMPLES_PER_PIXEL,
+    );
 
     const LOOK_FROM: Vec3 = Vec3 {
         x: -3.0,
@@ -112,7 +144,7 @@ fn main() -> Result<()> {
 
     let dist_to_focus: f64 = (LOOK_FROM - LOOK_AT).magnitude();
 
-    let mut camera = Camera::new();
+    let mut camera = PerspectiveCamera::new();
     camera
         .look_from(LOOK_FROM)
         .look_at(LOOK_AT)
@@ -123,25 +155,8 @@ fn main() -> Result<()> {
         .set_focus_distance(dist_to_focus)
         .update();
 
-    let mut bar = progress::Bar::new();
-
-    let pixel_colors: Vec<Color> = iproduct!((0..IMAGE_HEIGHT).rev(), 0..IMAGE_WIDTH)
-        .enumerate()
-        .map(|(i, (y, x))| {
-            bar.reach_percent((100.0 * i as f64 / IMAGE_AREA as f64) as i32);
-            world.pixel_color(x, y, IMAGE_WIDTH, IMAGE_HEIGHT, &camera, &mut rng)
-        })
-        .collect();
-
-    bar.reach_percent(100);
-    bar.jobs_done();
-
-    let mut file = File::create("test.ppm")?;
-    writeln!(file, "P3\n{} {}\n255", IMAGE_WIDTH, IMAGE_HEIGHT)?;
-    for color in pixel_colors {
-        writeln!(file, "{}", color)?;
-    }
-    drop(file);
+    let image = Renderer::new(IMAGE_WIDTH, IMAGE_HEIGHT).render(&world, &camera, &mut rng);
+    image.write_ppm("test.ppm")?;
 
     Ok(())
 }
@@ -1,12 +1,57 @@
 //! Material traits and simple material implementations.
+use std::f64::consts::PI;
+use std::rc::Rc;
+
 use rand::Rng;
 
+use crate::microfacet::{
+    evaluate_dielectric, fresnel_conductor, ggx_d, roughness_to_alpha, sample_dielectric,
+    sample_visible_normal, smith_g1, smith_g2, visible_normal_pdf, Frame,
+};
 use crate::{Collision, Ray, Vec3};
 
 /// A material which interacts with rays by reflecting or absorbing them.
 pub trait Material {
     /// Scatter an incoming ray.
+    ///
+    /// Returns the scattered ray and the attenuation by which its color is multiplied.
     fn scatter(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3)>;
+
+    /// Scatter an incoming ray as by `scatter`, also returning whether it was scattered by a
+    /// specular lobe which `evaluate` and `pdf` do not represent.
+    ///
+    /// Materials mixing specular and non-specular lobes override this to report the lobe chosen.
+    fn scatter_lobe(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3, bool)> {
+        self.scatter(ray_in, collision)
+            .map(|(scattered, attenuation)| (scattered, attenuation, self.is_specular()))
+    }
+
+    /// Scattering function (BSDF) multiplied by the cosine of the angle between `direction` and
+    /// the surface normal, for light leaving along the reverse of `ray_in` and arriving from
+    /// `direction`.
+    ///
+    /// Zero for specular materials, which only scatter light through `scatter`.
+    fn evaluate(&self, _ray_in: &Ray, _collision: &Collision, _direction: &Vec3) -> Vec3 {
+        Vec3::zero()
+    }
+
+    /// Solid angle density with which `scatter` produces rays along `direction`.
+    ///
+    /// Zero for specular materials.
+    fn pdf(&self, _ray_in: &Ray, _collision: &Collision, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    /// Whether the material only scatters into discrete directions which `evaluate` and `pdf` do
+    /// not represent.
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    /// Radiance emitted from the surface along the reverse of `ray_in`.
+    fn emitted(&self, _ray_in: &Ray, _collision: &Collision) -> Vec3 {
+        Vec3::zero()
+    }
 }
 
 /// Simple lambertian material.
@@ -26,6 +71,15 @@ impl Material for Lambert {
         let scattered = Ray::new(collision.point, scatter_direction);
         Some((scattered, self.albedo))
     }
+
+    fn evaluate(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> Vec3 {
+        self.albedo * self.pdf(ray_in, collision, direction)
+    }
+
+    fn pdf(&self, _ray_in: &Ray, collision: &Collision, direction: &Vec3) -> f64 {
+        let cosine = direction.normalized().dot(&collision.normal);
+        cosine.max(0.0) / PI
+    }
 }
 
 /// Simple metallic material with surface scattering fuzz factor.
@@ -56,6 +110,10 @@ impl Material for Metal {
             None
         }
     }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
 }
 
 /// Simple dielectric material with refraction index.
@@ -99,4 +157,263 @@ impl Material for Dielectric {
             Some((Ray::new(collision.point, refracted), attenuation))
         }
     }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+}
+
+/// Rough conductor (metal) material with a GGX microfacet distribution.
+///
+/// Reflectance is given by the exact Fresnel equations for a complex index of refraction, with
+/// directions sampled from the distribution of visible microfacet normals.
+pub struct Conductor {
+    eta: Vec3,
+    k: Vec3,
+    alpha: f64,
+}
+
+impl Conductor {
+    /// Construct a conductor material.
+    ///
+    /// * `eta` - Real part of the index of refraction per color channel.
+    /// * `k` - Imaginary part (extinction coefficient) of the index of refraction per color channel.
+    /// * `roughness` - Perceptual roughness in [0.0, 1.0].
+    pub fn new(eta: Vec3, k: Vec3, roughness: f64) -> Self {
+        Self {
+            eta,
+            k,
+            alpha: roughness_to_alpha(roughness),
+        }
+    }
+
+    pub fn gold(roughness: f64) -> Self {
+        Self::new(
+            Vec3::new(0.143, 0.374, 1.442),
+            Vec3::new(3.983, 2.385, 1.603),
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f64) -> Self {
+        Self::new(
+            Vec3::new(0.200, 0.924, 1.102),
+            Vec3::new(3.912, 2.452, 2.142),
+            roughness,
+        )
+    }
+
+    pub fn aluminum(roughness: f64) -> Self {
+        Self::new(
+            Vec3::new(1.657, 0.880, 0.521),
+            Vec3::new(9.224, 6.270, 4.837),
+            roughness,
+        )
+    }
+
+    pub fn silver(roughness: f64) -> Self {
+        Self::new(
+            Vec3::new(0.155, 0.117, 0.138),
+            Vec3::new(4.828, 3.122, 2.147),
+            roughness,
+        )
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3)> {
+        let mut rng = rand::thread_rng();
+        let frame = Frame::new(collision.normal);
+        let wo = frame.to_local(&-ray_in.direction.normalized());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let m = sample_visible_normal(&wo, self.alpha, rng.gen(), rng.gen());
+        let wi = (-wo).reflect(m);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        let fresnel = fresnel_conductor(wo.dot(&m), &self.eta, &self.k);
+        let attenuation = fresnel * (smith_g2(&wo, &wi, self.alpha) / smith_g1(&wo, self.alpha));
+        Some((Ray::new(collision.point, frame.to_world(&wi)), attenuation))
+    }
+
+    fn evaluate(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> Vec3 {
+        let frame = Frame::new(collision.normal);
+        let wo = frame.to_local(&-ray_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Vec3::zero();
+        }
+        let m = (wo + wi).normalized();
+        let fresnel = fresnel_conductor(wo.dot(&m), &self.eta, &self.k);
+        fresnel * (ggx_d(&m, self.alpha) * smith_g2(&wo, &wi, self.alpha) / (4.0 * wo.z))
+    }
+
+    fn pdf(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> f64 {
+        let frame = Frame::new(collision.normal);
+        let wo = frame.to_local(&-ray_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let m = (wo + wi).normalized();
+        visible_normal_pdf(&wo, &m, self.alpha) / (4.0 * wo.dot(&m))
+    }
+}
+
+/// Rough dielectric (frosted glass) material with a GGX microfacet distribution.
+///
+/// Light is reflected or transmitted according to the exact dielectric Fresnel equations, and
+/// attenuated inside the medium following the Beer-Lambert law.
+pub struct RoughDielectric {
+    refraction_index: f64,
+    alpha: f64,
+    absorption: Vec3,
+}
+
+impl RoughDielectric {
+    /// Construct a rough dielectric material.
+    ///
+    /// * `refraction_index` - Index of refraction of the medium.
+    /// * `roughness` - Perceptual roughness in [0.0, 1.0].
+    /// * `absorption` - Absorption coefficient per unit distance per color channel.
+    pub fn new(refraction_index: f64, roughness: f64, absorption: Vec3) -> Self {
+        Self {
+            refraction_index,
+            alpha: roughness_to_alpha(roughness),
+            absorption,
+        }
+    }
+
+    /// Construct a tinted rough dielectric material.
+    ///
+    /// * `color` - Fraction of light per color channel remaining after traveling `distance`.
+    /// * `distance` - Distance through the medium at which the color is reached.
+    pub fn tinted(refraction_index: f64, roughness: f64, color: Vec3, distance: f64) -> Self {
+        let absorption = Vec3::new(
+            -color.x.max(1e-6).ln(),
+            -color.y.max(1e-6).ln(),
+            -color.z.max(1e-6).ln(),
+        ) / distance;
+        Self::new(refraction_index, roughness, absorption)
+    }
+
+    /// Ratio of the refractive index of the far side of the surface to the near side.
+    fn eta(&self, collision: &Collision) -> f64 {
+        if collision.front_face {
+            self.refraction_index
+        } else {
+            1.0 / self.refraction_index
+        }
+    }
+
+    /// Beer-Lambert transmittance along an incoming ray, which passed through the medium if it
+    /// collided with the back face.
+    fn transmittance(&self, ray_in: &Ray, collision: &Collision) -> Vec3 {
+        if collision.front_face {
+            return Vec3::one();
+        }
+        let distance = collision.t * ray_in.direction.magnitude();
+        Vec3::new(
+            (-self.absorption.x * distance).exp(),
+            (-self.absorption.y * distance).exp(),
+            (-self.absorption.z * distance).exp(),
+        )
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3)> {
+        let mut rng = rand::thread_rng();
+        let frame = Frame::new(collision.normal);
+        let wo = frame.to_local(&-ray_in.direction.normalized());
+        let wi = sample_dielectric(
+            &wo,
+            self.eta(collision),
+            self.alpha,
+            rng.gen(),
+            rng.gen(),
+            rng.gen(),
+        )?;
+        let attenuation = self.transmittance(ray_in, collision)
+            * (smith_g2(&wo, &wi, self.alpha) / smith_g1(&wo, self.alpha));
+        Some((Ray::new(collision.point, frame.to_world(&wi)), attenuation))
+    }
+
+    fn evaluate(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> Vec3 {
+        let frame = Frame::new(collision.normal);
+        let wo = frame.to_local(&-ray_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        let (value, _) = evaluate_dielectric(&wo, &wi, self.eta(collision), self.alpha);
+        self.transmittance(ray_in, collision) * value
+    }
+
+    fn pdf(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> f64 {
+        let frame = Frame::new(collision.normal);
+        let wo = frame.to_local(&-ray_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        let (_, pdf) = evaluate_dielectric(&wo, &wi, self.eta(collision), self.alpha);
+        pdf
+    }
+}
+
+/// Surface emitting light uniformly from its front face, scattering light with another material.
+///
+/// Emitted light is only found by scattered rays, so small emitters converge slowly unless their
+/// surfaces are also sampled by an `AreaLight`.
+pub struct Emissive {
+    material: Rc<dyn Material>,
+    radiance: Vec3,
+    two_sided: bool,
+}
+
+impl Emissive {
+    /// Construct an emissive material.
+    ///
+    /// * `material` - Material scattering light arriving at the surface.
+    /// * `radiance` - Radiance emitted in every direction.
+    pub fn new(material: Rc<dyn Material>, radiance: Vec3) -> Self {
+        Self {
+            material,
+            radiance,
+            two_sided: false,
+        }
+    }
+
+    /// Set whether light is also emitted from the back face.
+    pub fn set_two_sided(&mut self, two_sided: bool) -> &mut Self {
+        self.two_sided = two_sided;
+        self
+    }
+}
+
+impl Material for Emissive {
+    fn scatter(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3)> {
+        self.material.scatter(ray_in, collision)
+    }
+
+    fn evaluate(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> Vec3 {
+        self.material.evaluate(ray_in, collision, direction)
+    }
+
+    fn pdf(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> f64 {
+        self.material.pdf(ray_in, collision, direction)
+    }
+
+    fn scatter_lobe(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3, bool)> {
+        self.material.scatter_lobe(ray_in, collision)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.material.is_specular()
+    }
+
+    fn emitted(&self, _ray_in: &Ray, collision: &Collision) -> Vec3 {
+        if collision.front_face || self.two_sided {
+            self.radiance
+        } else {
+            Vec3::zero()
+        }
+    }
 }
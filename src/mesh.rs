@@ -0,0 +1,238 @@
+//! Triangle meshes.
+use std::rc::Rc;
+
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::light::Surface;
+use crate::transform::Transform;
+use crate::{Collidable, Collision, Material, Ray, Vec3};
+
+/// Indexed triangle mesh with optional per-vertex attributes.
+///
+/// Triangles wind counter-clockwise about their outward normal.
+pub struct Mesh {
+    positions: Vec<Vec3>,
+    indices: Vec<[usize; 3]>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<(f64, f64)>>,
+    colors: Option<Vec<Vec3>>,
+    material: Rc<dyn Material>,
+}
+
+impl Mesh {
+    /// Construct a mesh.
+    ///
+    /// * `positions` - Vertex positions.
+    /// * `indices` - Vertex indices of each triangle.
+    pub fn new(positions: Vec<Vec3>, indices: Vec<[usize; 3]>, material: Rc<dyn Material>) -> Self {
+        Self {
+            positions,
+            indices,
+            normals: None,
+            uvs: None,
+            colors: None,
+            material,
+        }
+    }
+
+    /// Set vertex normals, interpolated for smooth shading.
+    pub fn set_normals(&mut self, normals: Vec<Vec3>) -> &mut Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    /// Set vertex surface coordinates.
+    pub fn set_uvs(&mut self, uvs: Vec<(f64, f64)>) -> &mut Self {
+        self.uvs = Some(uvs);
+        self
+    }
+
+    /// Set linear vertex colors, exposed to materials through `VertexColor`.
+    pub fn set_colors(&mut self, colors: Vec<Vec3>) -> &mut Self {
+        self.colors = Some(colors);
+        self
+    }
+
+    /// Set the material of all triangles.
+    pub fn set_material(&mut self, material: Rc<dyn Material>) -> &mut Self {
+        self.material = material;
+        self
+    }
+
+    /// Transform the vertices of the mesh.
+    pub fn transform(&mut self, transform: &Transform) -> &mut Self {
+        for p in self.positions.iter_mut() {
+            *p = transform.point(p);
+        }
+        if let Some(normals) = &mut self.normals {
+            for n in normals.iter_mut() {
+                *n = transform.normal(n).normalized();
+            }
+        }
+        // Keep triangles wound counter-clockwise about their outward normals.
+        if transform.swaps_handedness() {
+            self.flip();
+        }
+        self
+    }
+
+    /// Reverse the winding of triangles, turning them inside out.
+    pub fn flip(&mut self) -> &mut Self {
+        for triangle in self.indices.iter_mut() {
+            triangle.swap(1, 2);
+        }
+        self
+    }
+
+    /// Vertex positions.
+    pub fn positions(&self) -> &[Vec3] {
+        &self.positions
+    }
+
+    /// Vertex indices of each triangle.
+    pub fn indices(&self) -> &[[usize; 3]] {
+        &self.indices
+    }
+
+    /// Collidables for each triangle, sharing the mesh.
+    pub fn triangles(self: Rc<Self>) -> Vec<Box<dyn Collidable>> {
+        (0..self.indices.len())
+            .map(|index| {
+                Box::new(Triangle {
+                    mesh: self.clone(),
+                    index,
+                }) as Box<dyn Collidable>
+            })
+            .collect()
+    }
+
+    /// Surfaces of each triangle, sharing the mesh, for sampling as an area light.
+    pub fn surfaces(self: Rc<Self>) -> Vec<Box<dyn Surface>> {
+        (0..self.indices.len())
+            .map(|index| {
+                Box::new(Triangle {
+                    mesh: self.clone(),
+                    index,
+                }) as Box<dyn Surface>
+            })
+            .collect()
+    }
+
+    /// Collidable of all triangles with a bounding volume hierarchy.
+    pub fn into_bvh(self) -> Bvh {
+        Bvh::new(Rc::new(self).triangles())
+    }
+}
+
+/// Collidable triangle of a mesh.
+pub struct Triangle {
+    mesh: Rc<Mesh>,
+    index: usize,
+}
+
+impl Triangle {
+    /// Vertex positions.
+    fn vertices(&self) -> (Vec3, Vec3, Vec3) {
+        let [i0, i1, i2] = self.mesh.indices[self.index];
+        let p = &self.mesh.positions;
+        (p[i0], p[i1], p[i2])
+    }
+
+    /// Interpolate a vertex attribute with barycentric coordinates.
+    fn interpolate<T>(&self, values: &[T], (b1, b2): (f64, f64), map: impl Fn(&T) -> Vec3) -> Vec3 {
+        let [i0, i1, i2] = self.mesh.indices[self.index];
+        map(&values[i0]) * (1.0 - b1 - b2) + map(&values[i1]) * b1 + map(&values[i2]) * b2
+    }
+}
+
+impl Collidable for Triangle {
+    /// Möller-Trumbore ray-triangle intersection.
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        let mesh = &self.mesh;
+        let [i0, i1, i2] = mesh.indices[self.index];
+        let (p0, p1, p2) = (mesh.positions[i0], mesh.positions[i1], mesh.positions[i2]);
+        let e1 = p1 - p0;
+        let e2 = p2 - p0;
+        let pvec = ray.direction.cross(&e2);
+        let determinant = e1.dot(&pvec);
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+        let tvec = ray.point - p0;
+        let b1 = tvec.dot(&pvec) * inverse;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+        let qvec = tvec.cross(&e1);
+        let b2 = ray.direction.dot(&qvec) * inverse;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+        let t = e2.dot(&qvec) * inverse;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+
+        // Surface coordinates default to the barycentric coordinates.
+        let (uv, tangent) = match &mesh.uvs {
+            Some(uvs) => {
+                let (uv0, uv1, uv2) = (uvs[i0], uvs[i1], uvs[i2]);
+                let uv = self.interpolate(uvs, (b1, b2), |&(u, v)| Vec3::new(u, v, 0.0));
+                let (du1, dv1) = (uv1.0 - uv0.0, uv1.1 - uv0.1);
+                let (du2, dv2) = (uv2.0 - uv0.0, uv2.1 - uv0.1);
+                let r = du1 * dv2 - du2 * dv1;
+                let tangent = if r != 0.0 {
+                    (e1 * dv2 - e2 * dv1) / r
+                } else {
+                    Vec3::zero()
+                };
+                ((uv.x, uv.y), tangent)
+            }
+            None => ((b1, b2), e1),
+        };
+
+        let mut collision = Collision::new(
+            ray,
+            t,
+            ray.at(t),
+            e1.cross(&e2).normalized(),
+            uv,
+            tangent,
+            mesh.material.clone(),
+        );
+        if let Some(normals) = &mesh.normals {
+            // Keep the shading normal on the same side as the geometric normal.
+            let normal = self.interpolate(normals, (b1, b2), |n| *n).normalized();
+            collision.normal = if normal.dot(&collision.geometric_normal) < 0.0 {
+                -normal
+            } else {
+                normal
+            };
+        }
+        if let Some(colors) = &mesh.colors {
+            collision.color = Some(self.interpolate(colors, (b1, b2), |c| *c));
+        }
+        Some(collision)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (p0, p1, p2) = self.vertices();
+        Aabb::from_points(&[p0, p1, p2])
+    }
+}
+
+impl Surface for Triangle {
+    fn area(&self) -> f64 {
+        let (p0, p1, p2) = self.vertices();
+        0.5 * (p1 - p0).cross(&(p2 - p0)).magnitude()
+    }
+
+    fn sample_point(&self, u0: f64, u1: f64) -> (Vec3, Vec3) {
+        let (p0, p1, p2) = self.vertices();
+        let s = u0.sqrt();
+        let (b1, b2) = (u1 * s, 1.0 - s);
+        let point = p0 * (1.0 - b1 - b2) + p1 * b1 + p2 * b2;
+        (point, (p1 - p0).cross(&(p2 - p0)).normalized())
+    }
+}
@@ -0,0 +1,216 @@
+//! Microfacet distribution, masking-shadowing and Fresnel functions.
+//!
+//! Directions are expressed in a local shading frame with the surface normal along +Z.
+use std::f64::consts::PI;
+
+use crate::Vec3;
+
+/// Orthonormal shading frame about a surface normal.
+#[derive(Copy, Clone, Debug)]
+pub struct Frame {
+    pub s: Vec3,
+    pub t: Vec3,
+    pub n: Vec3,
+}
+
+impl Frame {
+    /// Construct an arbitrarily oriented frame about a unit normal.
+    pub fn new(n: Vec3) -> Self {
+        let (s, t) = n.orthonormal_basis();
+        Self { s, t, n }
+    }
+
+    /// Express a world space vector in the frame.
+    pub fn to_local(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(v.dot(&self.s), v.dot(&self.t), v.dot(&self.n))
+    }
+
+    /// Express a frame vector in world space.
+    pub fn to_world(&self, v: &Vec3) -> Vec3 {
+        self.s * v.x + self.t * v.y + self.n * v.z
+    }
+}
+
+/// Convert perceptual roughness in [0.0, 1.0] to the GGX α parameter.
+pub fn roughness_to_alpha(roughness: f64) -> f64 {
+    (roughness * roughness).max(1e-4)
+}
+
+/// GGX (Trowbridge-Reitz) distribution of microfacet normals.
+pub fn ggx_d(m: &Vec3, alpha: f64) -> f64 {
+    if m.z <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    let d = m.z * m.z * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+/// Smith Λ function for the GGX distribution.
+fn smith_lambda(v: &Vec3, alpha: f64) -> f64 {
+    let cos2 = v.z * v.z;
+    if cos2 <= 0.0 {
+        return f64::INFINITY;
+    }
+    let tan2 = (1.0 - cos2).max(0.0) / cos2;
+    ((1.0 + alpha * alpha * tan2).sqrt() - 1.0) * 0.5
+}
+
+/// Smith masking function for a single direction.
+pub fn smith_g1(v: &Vec3, alpha: f64) -> f64 {
+    1.0 / (1.0 + smith_lambda(v, alpha))
+}
+
+/// Height-correlated Smith masking-shadowing function for a pair of directions.
+pub fn smith_g2(wo: &Vec3, wi: &Vec3, alpha: f64) -> f64 {
+    1.0 / (1.0 + smith_lambda(wo, alpha) + smith_lambda(wi, alpha))
+}
+
+/// Sample a microfacet normal from the distribution of normals visible from `wo`.
+///
+/// ([source](https://jcgt.org/published/0007/04/01/))
+pub fn sample_visible_normal(wo: &Vec3, alpha: f64, u1: f64, u2: f64) -> Vec3 {
+    // Stretch the view direction to the hemisphere configuration.
+    let vh = Vec3::new(alpha * wo.x, alpha * wo.y, wo.z).normalized();
+    let length_squared = vh.x * vh.x + vh.y * vh.y;
+    let t1 = if length_squared > 0.0 {
+        Vec3::new(-vh.y, vh.x, 0.0) / length_squared.sqrt()
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let t2 = vh.cross(&t1);
+
+    // Sample the projected area of the visible hemisphere.
+    let r = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    let p1 = r * phi.cos();
+    let s = 0.5 * (1.0 + vh.z);
+    let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+    let nh = t1 * p1 + t2 * p2 + vh * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+    // Unstretch back to the ellipsoid configuration.
+    Vec3::new(alpha * nh.x, alpha * nh.y, nh.z.max(0.0)).normalized()
+}
+
+/// Density of a microfacet normal sampled by `sample_visible_normal`.
+pub fn visible_normal_pdf(wo: &Vec3, m: &Vec3, alpha: f64) -> f64 {
+    if wo.z <= 0.0 {
+        return 0.0;
+    }
+    smith_g1(wo, alpha) * wo.dot(m).max(0.0) * ggx_d(m, alpha) / wo.z
+}
+
+/// Sample a direction scattered by a rough dielectric interface, reflecting or refracting with
+/// the Fresnel reflectance of a microfacet normal sampled from those visible from `wo`.
+///
+/// * `wo` - Direction towards the near side (with a positive Z component).
+/// * `eta` - Ratio of the refractive index of the far side to the near side.
+/// * `u1`, `u2`, `u3` - Uniform random numbers.
+pub fn sample_dielectric(
+    wo: &Vec3,
+    eta: f64,
+    alpha: f64,
+    u1: f64,
+    u2: f64,
+    u3: f64,
+) -> Option<Vec3> {
+    if wo.z <= 0.0 {
+        return None;
+    }
+    let m = sample_visible_normal(wo, alpha, u1, u2);
+    let fresnel = fresnel_dielectric(wo.dot(&m), eta);
+    if u3 < fresnel {
+        let wi = (-*wo).reflect(m);
+        if wi.z > 0.0 {
+            Some(wi)
+        } else {
+            None
+        }
+    } else {
+        let wi = (-*wo).refract(m, 1.0 / eta);
+        if wi.z < 0.0 {
+            Some(wi)
+        } else {
+            None
+        }
+    }
+}
+
+/// Scattering function times cosine, and sampling density, of a rough dielectric interface for
+/// light arriving from `wi` and leaving towards `wo`.
+///
+/// * `wo` - Direction towards the near side (with a positive Z component).
+/// * `wi` - Direction towards either side.
+/// * `eta` - Ratio of the refractive index of the far side to the near side.
+pub fn evaluate_dielectric(wo: &Vec3, wi: &Vec3, eta: f64, alpha: f64) -> (f64, f64) {
+    if wo.z <= 0.0 || wi.z == 0.0 {
+        return (0.0, 0.0);
+    }
+    let reflect = wi.z > 0.0;
+    // Generalized half vector.
+    let m = if reflect { *wo + *wi } else { *wo + *wi * eta };
+    if m.magnitude_squared() == 0.0 {
+        return (0.0, 0.0);
+    }
+    let m = m.normalized();
+    let m = if m.z < 0.0 { -m } else { m };
+    // Discard back-facing microfacets.
+    if wo.dot(&m) <= 0.0 || (wi.dot(&m) > 0.0) != reflect {
+        return (0.0, 0.0);
+    }
+
+    let fresnel = fresnel_dielectric(wo.dot(&m), eta);
+    let (lobe, jacobian) = if reflect {
+        (fresnel, 1.0 / (4.0 * wo.dot(&m)))
+    } else {
+        let denominator = wi.dot(&m) + wo.dot(&m) / eta;
+        (
+            1.0 - fresnel,
+            wi.dot(&m).abs() / (denominator * denominator),
+        )
+    };
+    let pdf = lobe * visible_normal_pdf(wo, &m, alpha) * jacobian;
+    // The visible normal density with masking replaced by masking-shadowing.
+    let value = pdf * smith_g2(wo, wi, alpha) / smith_g1(wo, alpha);
+    (value, pdf)
+}
+
+/// Fresnel reflectance of a dielectric interface for unpolarized light.
+///
+/// * `cos_i` - Cosine of the incident angle on the side the light arrives from.
+/// * `eta` - Ratio of the refractive index of the transmitted side to the incident side.
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+/// Fresnel reflectance of a conductor interface with complex refractive index `eta + i k` per
+/// color channel, for unpolarized light.
+pub fn fresnel_conductor(cos_i: f64, eta: &Vec3, k: &Vec3) -> Vec3 {
+    let channel = |eta: f64, k: f64| {
+        let cos2 = (cos_i * cos_i).clamp(0.0, 1.0);
+        let sin2 = 1.0 - cos2;
+        let t0 = eta * eta - k * k - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let t2 = 2.0 * cos_i.abs() * a;
+        let r_perpendicular = (t1 - t2) / (t1 + t2);
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let r_parallel = r_perpendicular * (t3 - t4) / (t3 + t4);
+        0.5 * (r_parallel + r_perpendicular)
+    };
+    Vec3::new(
+        channel(eta.x, k.x),
+        channel(eta.y, k.y),
+        channel(eta.z, k.z),
+    )
+}
@@ -0,0 +1,996 @@
+//! pbrt-v3 scene importer for a practical subset of the format.
+//!
+//! Supported directives are `Camera` (perspective), `Film`, `Sampler`, `Integrator`, the
+//! transformation directives (`Identity`, `Translate`, `Scale`, `Rotate`, `LookAt`,
+//! `ConcatTransform`, `Transform`, `CoordinateSystem`, `CoordSysTransform`), `WorldBegin`,
+//! `AttributeBegin`/`AttributeEnd`, `TransformBegin`/`TransformEnd`, `ReverseOrientation`,
+//! `Shape` (sphere, disk, trianglemesh, plymesh), `Material` (matte, metal, glass, uber, mirror),
+//! `MakeNamedMaterial`/`NamedMaterial`, `AreaLightSource` (diffuse), `LightSource` (point, spot,
+//! distant, constant infinite), `ObjectBegin`/`ObjectEnd`/`ObjectInstance` and `Include`. Other
+//! directives, shapes and parameters are skipped, and unknown materials are imported as the
+//! default matte material.
+//!
+//! Objects are built once into a hierarchy shared by each of their instances. Area lights on
+//! spheres, disks and meshes outside of objects are sampled directly, as in pbrt.
+//!
+//! pbrt uses a left-handed coordinate system, so unless the camera transformation already mirrors
+//! space the world is mirrored about the camera's vertical plane to match images rendered by pbrt.
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::bvh::Bvh;
+use crate::camera::PerspectiveCamera;
+use crate::collide::{Disk, Sphere};
+use crate::environment::EnvironmentMap;
+use crate::instance::Instance;
+use crate::light::{AreaLight, DirectionalLight, PointLight, SpotLight, Surface};
+use crate::material::{Conductor, Dielectric, Emissive, Lambert, Metal, RoughDielectric};
+use crate::mesh::Mesh;
+use crate::principled::Principled;
+use crate::transform::Transform;
+use crate::{ply, Collidable, Environment, Light, Material, Vec3, World};
+
+const T_MIN: f64 = 0.001;
+const T_MAX: f64 = f64::INFINITY;
+
+/// Construct an invalid data error.
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("pbrt: {}", message))
+}
+
+/// Camera, world and image size of a pbrt scene.
+pub struct PbrtScene {
+    pub camera: PerspectiveCamera,
+    pub world: World,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Load a scene from a pbrt file.
+///
+/// Included files and PLY meshes are found relative to the directory of the scene file.
+pub fn open<P: AsRef<Path>>(path: P) -> Result<PbrtScene> {
+    let path = path.as_ref();
+    let directory = path.parent().map_or_else(PathBuf::new, Path::to_path_buf);
+    parse(&fs::read_to_string(path)?, directory)
+}
+
+/// Parse a scene from the contents of a pbrt file.
+///
+/// * `directory` - Directory from which included files and PLY meshes are found.
+pub fn parse(source: &str, directory: PathBuf) -> Result<PbrtScene> {
+    let mut importer = Importer::new(directory);
+    importer.run(&tokenize(source)?)?;
+    importer.finish()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    String(String),
+    Number(f64),
+    Open,
+    Close,
+}
+
+/// Split a scene description into tokens, dropping comments.
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            _ if c.is_whitespace() => {}
+            '#' => while chars.next_if(|&(_, c)| c != '\n').is_some() {},
+            '[' => tokens.push(Token::Open),
+            ']' => tokens.push(Token::Close),
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, c)) => string.push(c),
+                        None => return Err(invalid("unterminated string")),
+                    }
+                }
+                tokens.push(Token::String(string));
+            }
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) =
+                    chars.next_if(|&(_, c)| !c.is_whitespace() && !"[]\"#".contains(c))
+                {
+                    end = i + c.len_utf8();
+                }
+                let word = &source[start..end];
+                tokens.push(if c.is_ascii_alphabetic() {
+                    Token::Word(word.to_string())
+                } else {
+                    Token::Number(
+                        word.parse()
+                            .map_err(|_| invalid(&format!("invalid number `{}`", word)))?,
+                    )
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Directive argument, either a single value or a bracketed list of values.
+#[derive(Clone, Debug)]
+enum Argument {
+    Number(f64),
+    String(String),
+    List(Vec<Argument>),
+}
+
+impl Argument {
+    /// Numbers of the argument.
+    fn numbers(&self) -> Vec<f64> {
+        match self {
+            Argument::Number(x) => vec![*x],
+            Argument::String(_) => Vec::new(),
+            Argument::List(items) => items.iter().flat_map(Argument::numbers).collect(),
+        }
+    }
+
+    /// Strings of the argument, including unquoted booleans.
+    fn strings(&self) -> Vec<String> {
+        match self {
+            Argument::Number(_) => Vec::new(),
+            Argument::String(s) => vec![s.clone()],
+            Argument::List(items) => items.iter().flat_map(Argument::strings).collect(),
+        }
+    }
+}
+
+/// Typed parameter, e.g. `"float radius" [ 1 ]`.
+struct Parameter {
+    kind: String,
+    name: String,
+    value: Argument,
+}
+
+/// Parameter list of a directive.
+#[derive(Default)]
+struct Parameters(Vec<Parameter>);
+
+impl Parameters {
+    /// Parse the parameters following the leading arguments of a directive.
+    fn parse(arguments: &[Argument]) -> Result<Self> {
+        let mut parameters = Vec::new();
+        for pair in arguments.chunks(2) {
+            match pair {
+                [Argument::String(declaration), value] => {
+                    let words: Vec<&str> = declaration.split_whitespace().collect();
+                    match words.as_slice() {
+                        [kind, name] => parameters.push(Parameter {
+                            kind: kind.to_string(),
+                            name: name.to_string(),
+                            value: value.clone(),
+                        }),
+                        _ => {
+                            return Err(invalid(&format!(
+                                "invalid parameter declaration `{}`",
+                                declaration
+                            )))
+                        }
+                    }
+                }
+                _ => return Err(invalid("invalid parameter list")),
+            }
+        }
+        Ok(Self(parameters))
+    }
+
+    fn find(&self, name: &str) -> Option<&Parameter> {
+        self.0.iter().find(|p| p.name == name)
+    }
+
+    fn numbers(&self, name: &str) -> Option<Vec<f64>> {
+        self.find(name).map(|p| p.value.numbers())
+    }
+
+    fn float(&self, name: &str, default: f64) -> f64 {
+        self.numbers(name)
+            .and_then(|values| values.first().copied())
+            .unwrap_or(default)
+    }
+
+    fn string(&self, name: &str) -> Option<String> {
+        self.find(name)
+            .and_then(|p| p.value.strings().into_iter().next())
+    }
+
+    fn bool(&self, name: &str, default: bool) -> bool {
+        self.string(name).map_or(default, |s| s == "true")
+    }
+
+    fn point(&self, name: &str, default: Vec3) -> Vec3 {
+        match self.numbers(name).as_deref() {
+            Some([x, y, z, ..]) => Vec3::new(*x, *y, *z),
+            _ => default,
+        }
+    }
+
+    /// RGB color, with spectra given by other means (e.g. named spectrum files) left at the
+    /// default.
+    fn color(&self, name: &str, default: Vec3) -> Vec3 {
+        match self.find(name) {
+            Some(p) if p.kind == "rgb" || p.kind == "color" => match p.value.numbers()[..] {
+                [r, g, b, ..] => Vec3::new(r, g, b),
+                _ => default,
+            },
+            Some(p) if p.kind == "float" => {
+                let x = self.float(name, 0.0);
+                Vec3::new(x, x, x)
+            }
+            _ => default,
+        }
+    }
+
+    fn has_color(&self, name: &str) -> bool {
+        self.find(name)
+            .is_some_and(|p| p.kind == "rgb" || p.kind == "color")
+    }
+}
+
+/// Perceptual roughness (the square root of the GGX alpha) of a pbrt roughness value.
+///
+/// * `remap` - Whether the value is a perceptual roughness mapped to alpha as by pbrt, rather
+///   than alpha itself.
+fn perceptual_roughness(value: f64, remap: bool) -> f64 {
+    let alpha = if remap {
+        let x = value.max(1e-3).ln();
+        1.62142 + 0.819955 * x + 0.1734 * x * x + 0.0171201 * x.powi(3) + 0.000640711 * x.powi(4)
+    } else {
+        value
+    };
+    alpha.clamp(0.0, 1.0).sqrt()
+}
+
+/// Material of a `Material` or `MakeNamedMaterial` directive.
+fn material(kind: &str, parameters: &Parameters) -> Rc<dyn Material> {
+    let remap = parameters.bool("remaproughness", true);
+    let roughness = |default: f64| {
+        let roughness = parameters.float("roughness", default);
+        let u = parameters.float("uroughness", roughness);
+        let v = parameters.float("vroughness", roughness);
+        (u + v) / 2.0
+    };
+    match kind {
+        "metal" => {
+            let roughness = perceptual_roughness(roughness(0.01), remap);
+            if parameters.has_color("eta") && parameters.has_color("k") {
+                Rc::new(Conductor::new(
+                    parameters.color("eta", Vec3::one()),
+                    parameters.color("k", Vec3::one()),
+                    roughness,
+                ))
+            } else {
+                Rc::new(Conductor::copper(roughness))
+            }
+        }
+        "glass" => {
+            let index = parameters.float("index", parameters.float("eta", 1.5));
+            match roughness(0.0) {
+                r if r > 0.0 => Rc::new(RoughDielectric::new(
+                    index,
+                    perceptual_roughness(r, remap),
+                    Vec3::zero(),
+                )),
+                _ => Rc::new(Dielectric::new(index)),
+            }
+        }
+        "mirror" => Rc::new(Metal::new(parameters.color("Kr", Vec3::one() * 0.9), 0.0)),
+        // Specular reflection is approximated by the principled specular lobe, which reflects
+        // 4% head on at its default of 0.5 against the 0.25 default of `Ks`.
+        "uber" => {
+            let specular = parameters.color("Ks", Vec3::one() * 0.25).luminance() * 2.0;
+            let mut principled =
+                Principled::new(Rc::new(parameters.color("Kd", Vec3::one() * 0.25)));
+            principled
+                .set_roughness(Rc::new(perceptual_roughness(roughness(0.1), remap)))
+                .set_specular(Rc::new(specular.min(1.0)))
+                .set_refraction_index(parameters.float("index", 1.5));
+            Rc::new(principled)
+        }
+        _ => Rc::new(Lambert::new(parameters.color("Kd", Vec3::one() * 0.5))),
+    }
+}
+
+/// Transformation of a row-major matrix given in pbrt's column-major order.
+fn matrix_transform(values: &[f64]) -> Result<Transform> {
+    if values.len() != 16 {
+        return Err(invalid("transformation matrix without 16 values"));
+    }
+    let mut matrix = [[0.0; 4]; 4];
+    for (i, row) in matrix.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = values[4 * j + i];
+        }
+    }
+    Transform::from_matrix(matrix).ok_or_else(|| invalid("singular transformation matrix"))
+}
+
+/// Camera from world transformation of a `LookAt` directive.
+fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Result<Transform> {
+    let direction = (target - eye).normalized();
+    let right = up.normalized().cross(&direction).normalized();
+    let up = direction.cross(&right);
+    let matrix = [
+        [right.x, up.x, direction.x, eye.x],
+        [right.y, up.y, direction.y, eye.y],
+        [right.z, up.z, direction.z, eye.z],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+    Transform::from_matrix(matrix)
+        .map(|camera_to_world| camera_to_world.inverse())
+        .ok_or_else(|| invalid("degenerate LookAt"))
+}
+
+/// Reflection about a plane.
+fn mirror(point: Vec3, normal: Vec3) -> Result<Transform> {
+    let n = normal.normalized();
+    let mut matrix = [[0.0; 4]; 4];
+    for (i, row) in matrix.iter_mut().enumerate().take(3) {
+        for (j, value) in row.iter_mut().enumerate().take(3) {
+            *value = if i == j { 1.0 } else { 0.0 } - 2.0 * n[i] * n[j];
+        }
+    }
+    matrix[3][3] = 1.0;
+    let reflection = Transform::from_matrix(matrix).ok_or_else(|| invalid("degenerate camera"))?;
+    Ok(Transform::translate(-point)
+        .then(&reflection)
+        .then(&Transform::translate(point)))
+}
+
+/// Graphics state saved by `AttributeBegin`.
+#[derive(Clone)]
+struct Attributes {
+    transform: Transform,
+    material: Rc<dyn Material>,
+    /// Radiance and two-sidedness of the current area light.
+    area_light: Option<(Vec3, bool)>,
+    reverse_orientation: bool,
+}
+
+struct Importer {
+    directory: PathBuf,
+    attributes: Attributes,
+    attribute_stack: Vec<Attributes>,
+    transform_stack: Vec<Transform>,
+    named_materials: HashMap<String, Rc<dyn Material>>,
+    coordinate_systems: HashMap<String, Transform>,
+    /// Camera to world transformation and parameters.
+    camera: Option<(Transform, Parameters)>,
+    film: Parameters,
+    samples: usize,
+    max_depth: usize,
+    /// Reflection from pbrt's left-handed world, applied to everything in the world.
+    mirror: Transform,
+    collidables: Vec<Box<dyn Collidable>>,
+    /// Name and shapes of the object being defined.
+    object: Option<(String, Vec<Box<dyn Collidable>>)>,
+    objects: HashMap<String, Rc<dyn Collidable>>,
+    lights: Vec<Box<dyn Light>>,
+    area_lights: Vec<AreaLight>,
+    environment: Option<Box<dyn Environment>>,
+}
+
+impl Importer {
+    fn new(directory: PathBuf) -> Self {
+        Self {
+            directory,
+            attributes: Attributes {
+                transform: Transform::identity(),
+                material: material("matte", &Parameters::default()),
+                area_light: None,
+                reverse_orientation: false,
+            },
+            attribute_stack: Vec::new(),
+            transform_stack: Vec::new(),
+            named_materials: HashMap::new(),
+            coordinate_systems: HashMap::new(),
+            camera: None,
+            film: Parameters::default(),
+            samples: 16,
+            max_depth: 6,
+            mirror: Transform::identity(),
+            collidables: Vec::new(),
+            object: None,
+            objects: HashMap::new(),
+            lights: Vec::new(),
+            area_lights: Vec::new(),
+            environment: None,
+        }
+    }
+
+    /// Apply a transformation before the current transformation.
+    fn concatenate(&mut self, transform: Transform) {
+        self.attributes.transform = transform.then(&self.attributes.transform);
+    }
+
+    /// Current object to (mirrored) world transformation.
+    ///
+    /// Shapes of objects being defined are left unmirrored, as their instances are mirrored.
+    fn object_to_world(&self) -> Transform {
+        if self.object.is_some() {
+            self.attributes.transform
+        } else {
+            self.attributes.transform.then(&self.mirror)
+        }
+    }
+
+    /// Add a collidable to the world or to the object being defined.
+    fn add(&mut self, collidable: Box<dyn Collidable>) {
+        match &mut self.object {
+            Some((_, collidables)) => collidables.push(collidable),
+            None => self.collidables.push(collidable),
+        }
+    }
+
+    /// Execute the directives of a token stream.
+    fn run(&mut self, tokens: &[Token]) -> Result<()> {
+        let mut i = 0;
+        while i < tokens.len() {
+            let directive = match &tokens[i] {
+                Token::Word(word) => word.as_str(),
+                token => return Err(invalid(&format!("unexpected token {:?}", token))),
+            };
+            i += 1;
+            let mut arguments = Vec::new();
+            while i < tokens.len() {
+                let argument = match &tokens[i] {
+                    Token::Word(word) if word == "true" || word == "false" => {
+                        Argument::String(word.clone())
+                    }
+                    Token::Word(_) => break,
+                    Token::Number(x) => Argument::Number(*x),
+                    Token::String(s) => Argument::String(s.clone()),
+                    Token::Open => {
+                        let mut items = Vec::new();
+                        loop {
+                            i += 1;
+                            match tokens.get(i) {
+                                Some(Token::Close) => break,
+                                Some(Token::Number(x)) => items.push(Argument::Number(*x)),
+                                Some(Token::String(s)) | Some(Token::Word(s)) => {
+                                    items.push(Argument::String(s.clone()))
+                                }
+                                _ => return Err(invalid("unterminated list")),
+                            }
+                        }
+                        Argument::List(items)
+                    }
+                    Token::Close => return Err(invalid("unexpected `]`")),
+                };
+                arguments.push(argument);
+                i += 1;
+            }
+            self.directive(directive, &arguments)?;
+        }
+        Ok(())
+    }
+
+    fn directive(&mut self, directive: &str, arguments: &[Argument]) -> Result<()> {
+        let numbers: Vec<f64> = arguments.iter().flat_map(Argument::numbers).collect();
+        let name = arguments
+            .first()
+            .and_then(|a| a.strings().into_iter().next());
+        let parameters = || Parameters::parse(arguments.get(1..).unwrap_or_default());
+        let count = |n: usize| {
+            if numbers.len() == n {
+                Ok(())
+            } else {
+                Err(invalid(&format!("{} expects {} numbers", directive, n)))
+            }
+        };
+        let vector = |i: usize| Vec3::new(numbers[i], numbers[i + 1], numbers[i + 2]);
+
+        match directive {
+            "Include" => {
+                let path = self.directory.join(name.unwrap_or_default());
+                self.run(&tokenize(&fs::read_to_string(path)?)?)?;
+            }
+            "Identity" => self.attributes.transform = Transform::identity(),
+            "Translate" => {
+                count(3)?;
+                self.concatenate(Transform::translate(vector(0)));
+            }
+            "Scale" => {
+                count(3)?;
+                self.concatenate(Transform::scale(vector(0)));
+            }
+            "Rotate" => {
+                count(4)?;
+                self.concatenate(Transform::rotate(numbers[0], vector(1)));
+            }
+            "LookAt" => {
+                count(9)?;
+                self.concatenate(look_at(vector(0), vector(3), vector(6))?);
+            }
+            "ConcatTransform" => self.concatenate(matrix_transform(&numbers)?),
+            "Transform" => self.attributes.transform = matrix_transform(&numbers)?,
+            "CoordinateSystem" => {
+                self.coordinate_systems
+                    .insert(name.unwrap_or_default(), self.attributes.transform);
+            }
+            "CoordSysTransform" => {
+                if let Some(transform) = self.coordinate_systems.get(&name.unwrap_or_default()) {
+                    self.attributes.transform = *transform;
+                }
+            }
+            "Camera" => {
+                let camera_to_world = self.attributes.transform.inverse();
+                self.coordinate_systems
+                    .insert("camera".to_string(), camera_to_world);
+                self.camera = Some((camera_to_world, parameters()?));
+            }
+            "Film" => self.film = parameters()?,
+            "Sampler" => {
+                self.samples = parameters()?.float("pixelsamples", 16.0).max(1.0) as usize;
+            }
+            "Integrator" => {
+                self.max_depth = parameters()?.float("maxdepth", 5.0).max(0.0) as usize + 1;
+            }
+            "WorldBegin" => {
+                self.attributes.transform = Transform::identity();
+                self.coordinate_systems
+                    .insert("world".to_string(), Transform::identity());
+                if let Some((camera_to_world, _)) = &self.camera {
+                    if !camera_to_world.swaps_handedness() {
+                        let origin = camera_to_world.point(&Vec3::zero());
+                        let right = camera_to_world.vector(&Vec3::new(1.0, 0.0, 0.0));
+                        self.mirror = mirror(origin, right)?;
+                    }
+                }
+            }
+            "AttributeBegin" => self.attribute_stack.push(self.attributes.clone()),
+            "AttributeEnd" => {
+                self.attributes = self
+                    .attribute_stack
+                    .pop()
+                    .ok_or_else(|| invalid("unmatched AttributeEnd"))?;
+            }
+            "TransformBegin" => self.transform_stack.push(self.attributes.transform),
+            "TransformEnd" => {
+                self.attributes.transform = self
+                    .transform_stack
+                    .pop()
+                    .ok_or_else(|| invalid("unmatched TransformEnd"))?;
+            }
+            "ReverseOrientation" => {
+                self.attributes.reverse_orientation = !self.attributes.reverse_orientation
+            }
+            "Material" => {
+                self.attributes.material = material(&name.unwrap_or_default(), &parameters()?)
+            }
+            "MakeNamedMaterial" => {
+                let parameters = parameters()?;
+                let kind = parameters.string("type").unwrap_or_default();
+                self.named_materials
+                    .insert(name.unwrap_or_default(), material(&kind, &parameters));
+            }
+            "NamedMaterial" => {
+                let name = name.unwrap_or_default();
+                self.attributes.material = self
+                    .named_materials
+                    .get(&name)
+                    .ok_or_else(|| invalid(&format!("unknown named material `{}`", name)))?
+                    .clone();
+            }
+            "AreaLightSource" => {
+                let parameters = parameters()?;
+                let radiance = parameters
+                    .color("L", Vec3::one())
+                    .hadamard_product(&parameters.color("scale", Vec3::one()));
+                self.attributes.area_light = Some((radiance, parameters.bool("twosided", false)));
+            }
+            "LightSource" => self.light(&name.unwrap_or_default(), &parameters()?),
+            "Shape" => self.shape(&name.unwrap_or_default(), &parameters()?)?,
+            "ObjectBegin" => {
+                if self.object.is_some() {
+                    return Err(invalid("nested ObjectBegin"));
+                }
+                self.attribute_stack.push(self.attributes.clone());
+                self.object = Some((name.unwrap_or_default(), Vec::new()));
+            }
+            "ObjectEnd" => {
+                let (name, collidables) = self
+                    .object
+                    .take()
+                    .ok_or_else(|| invalid("unmatched ObjectEnd"))?;
+                self.objects.insert(name, Rc::new(Bvh::new(collidables)));
+                self.attributes = self
+                    .attribute_stack
+                    .pop()
+                    .ok_or_else(|| invalid("unmatched ObjectEnd"))?;
+            }
+            "ObjectInstance" => {
+                let name = name.unwrap_or_default();
+                let object = self
+                    .objects
+                    .get(&name)
+                    .ok_or_else(|| invalid(&format!("unknown object `{}`", name)))?
+                    .clone();
+                let transform = self.object_to_world();
+                self.add(Box::new(Instance::new(object, transform)));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn light(&mut self, kind: &str, parameters: &Parameters) {
+        let transform = self.object_to_world();
+        let scale = parameters.color("scale", Vec3::one());
+        let from = transform.point(&parameters.point("from", Vec3::zero()));
+        let to = transform.point(&parameters.point("to", Vec3::new(0.0, 0.0, 1.0)));
+        match kind {
+            "point" => {
+                let intensity = parameters.color("I", Vec3::one()).hadamard_product(&scale);
+                self.lights.push(Box::new(PointLight::new(from, intensity)));
+            }
+            "spot" => {
+                let intensity = parameters.color("I", Vec3::one()).hadamard_product(&scale);
+                let cone_angle = parameters.float("coneangle", 30.0);
+                let falloff_angle = parameters.float("conedeltaangle", 5.0);
+                self.lights.push(Box::new(SpotLight::new(
+                    from,
+                    to - from,
+                    intensity,
+                    cone_angle,
+                    falloff_angle,
+                )));
+            }
+            "distant" => {
+                let radiance = parameters.color("L", Vec3::one()).hadamard_product(&scale);
+                self.lights
+                    .push(Box::new(DirectionalLight::new(to - from, radiance)));
+            }
+            // Environment maps (`mapname`) are not supported, leaving a constant environment.
+            "infinite" => {
+                let radiance = parameters.color("L", Vec3::one()).hadamard_product(&scale);
+                self.environment = Some(Box::new(EnvironmentMap::new(1, 1, vec![radiance])));
+            }
+            _ => {}
+        }
+    }
+
+    fn shape(&mut self, kind: &str, parameters: &Parameters) -> Result<()> {
+        let transform = self.object_to_world();
+        let mut material = self.attributes.material.clone();
+        if let Some((radiance, two_sided)) = self.attributes.area_light {
+            let mut emissive = Emissive::new(material, radiance);
+            emissive.set_two_sided(two_sided);
+            material = Rc::new(emissive);
+        }
+        // pbrt flips the normals of quadrics placed by mirroring transformations.
+        let inside_out =
+            self.attributes.reverse_orientation ^ self.attributes.transform.swaps_handedness();
+
+        let (collidable, surfaces): (Box<dyn Collidable>, Vec<Box<dyn Surface>>) = match kind {
+            // Spheres and disks are assumed to be scaled uniformly.
+            "sphere" => {
+                let radius = parameters.float("radius", 1.0);
+                let radius = transform.vector(&Vec3::new(radius, 0.0, 0.0)).magnitude();
+                let sphere = Sphere::new(
+                    transform.point(&Vec3::zero()),
+                    if inside_out { -radius } else { radius },
+                    material.clone(),
+                );
+                (Box::new(sphere.clone()), vec![Box::new(sphere)])
+            }
+            "disk" => {
+                let radius = parameters.float("radius", 1.0);
+                let radius = transform.vector(&Vec3::new(radius, 0.0, 0.0)).magnitude();
+                let normal = transform.normal(&Vec3::new(0.0, 0.0, 1.0)).normalized();
+                let disk = Disk::new(
+                    transform.point(&Vec3::new(0.0, 0.0, parameters.float("height", 0.0))),
+                    if inside_out { -normal } else { normal },
+                    radius,
+                    material.clone(),
+                );
+                (Box::new(disk.clone()), vec![Box::new(disk)])
+            }
+            "trianglemesh" | "plymesh" => {
+                let mut mesh = if kind == "trianglemesh" {
+                    triangle_mesh(parameters, material.clone())?
+                } else {
+                    let path = self
+                        .directory
+                        .join(parameters.string("filename").unwrap_or_default());
+                    ply::open(path, material.clone())?
+                };
+                self.place(&mut mesh, &transform);
+                let mesh = Rc::new(mesh);
+                (
+                    Box::new(Bvh::new(mesh.clone().triangles())),
+                    mesh.surfaces(),
+                )
+            }
+            _ => return Ok(()),
+        };
+        self.add(collidable);
+        // pbrt does not support area lights in objects, whose shapes are not in world space.
+        if self.attributes.area_light.is_some() && self.object.is_none() {
+            self.area_lights.push(AreaLight::new(surfaces, material));
+        }
+        Ok(())
+    }
+
+    /// Transform a mesh into the world, reversing its orientation if requested.
+    fn place(&self, mesh: &mut Mesh, transform: &Transform) {
+        mesh.transform(transform);
+        if self.attributes.reverse_orientation {
+            mesh.flip();
+        }
+    }
+
+    /// Build the camera and world.
+    fn finish(self) -> Result<PbrtScene> {
+        let width = self.film.float("xresolution", 640.0) as usize;
+        let height = self.film.float("yresolution", 480.0) as usize;
+        let aspect_ratio = width as f64 / height as f64;
+
+        let (camera_to_world, parameters) = self.camera.ok_or_else(|| invalid("missing Camera"))?;
+        let origin = camera_to_world.point(&Vec3::zero());
+        let forward = camera_to_world.vector(&Vec3::new(0.0, 0.0, 1.0));
+        let up = camera_to_world.vector(&Vec3::new(0.0, 1.0, 0.0));
+        // The field of view spans the shorter image axis.
+        let fov = parameters.float("fov", 90.0);
+        let vertical_fov = if aspect_ratio < 1.0 {
+            2.0 * ((fov.to_radians() / 2.0).tan() / aspect_ratio)
+                .atan()
+                .to_degrees()
+        } else {
+            fov
+        };
+        // Ray lengths scale with the focus distance, which only matters with a lens.
+        let lens_radius = parameters.float("lensradius", 0.0);
+        let focus_distance = if lens_radius > 0.0 {
+            parameters.float("focaldistance", 1e6)
+        } else {
+            1.0
+        };
+        let mut camera = PerspectiveCamera::new();
+        camera
+            .look_from(origin)
+            .look_at(origin + forward)
+            .set_up(up)
+            .set_vertical_fov(vertical_fov)
+            .set_aspect_ratio(aspect_ratio)
+            .set_aperture(2.0 * lens_radius)
+            .set_focus_distance(focus_distance)
+            .update();
+
+        let environment = self
+            .environment
+            .unwrap_or_else(|| Box::new(EnvironmentMap::new(1, 1, vec![Vec3::zero()])));
+        let mut world = World::new(
+            environment,
+            vec![Box::new(Bvh::new(self.collidables))],
+            self.lights,
+            T_MIN,
+            T_MAX,
+            self.max_depth,
+            self.samples,
+        );
+        world.set_area_lights(self.area_lights);
+        Ok(PbrtScene {
+            camera,
+            world,
+            width,
+            height,
+        })
+    }
+}
+
+/// Mesh of a `trianglemesh` shape.
+fn triangle_mesh(parameters: &Parameters, material: Rc<dyn Material>) -> Result<Mesh> {
+    let positions: Vec<Vec3> = parameters
+        .numbers("P")
+        .ok_or_else(|| invalid("trianglemesh without P"))?
+        .chunks_exact(3)
+        .map(|p| Vec3::new(p[0], p[1], p[2]))
+        .collect();
+    let indices: Vec<[usize; 3]> = match parameters.numbers("indices") {
+        Some(indices) => indices
+            .chunks_exact(3)
+            .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+            .collect(),
+        None if positions.len() == 3 => vec![[0, 1, 2]],
+        None => return Err(invalid("trianglemesh without indices")),
+    };
+    if indices.iter().flatten().any(|&i| i >= positions.len()) {
+        return Err(invalid("trianglemesh vertex index out of range"));
+    }
+    let normals = parameters.numbers("N");
+    let uvs = parameters
+        .numbers("uv")
+        .or_else(|| parameters.numbers("st"));
+
+    let vertices = positions.len();
+    let mut mesh = Mesh::new(positions, indices, material);
+    if let Some(normals) = normals.filter(|n| n.len() == 3 * vertices) {
+        mesh.set_normals(
+            normals
+                .chunks_exact(3)
+                .map(|n| Vec3::new(n[0], n[1], n[2]))
+                .collect(),
+        );
+    }
+    if let Some(uvs) = uvs.filter(|uv| uv.len() == 2 * vertices) {
+        mesh.set_uvs(uvs.chunks_exact(2).map(|uv| (uv[0], uv[1])).collect());
+    }
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FindCollision, Ray};
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).magnitude() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    /// Importer after running some directives.
+    fn run(source: &str) -> Result<Importer> {
+        let mut importer = Importer::new(PathBuf::new());
+        importer.run(&tokenize(source)?)?;
+        Ok(importer)
+    }
+
+    #[test]
+    fn tokenize_comments_strings_and_brackets() {
+        let tokens = tokenize(
+            "Shape \"sphere\" # a comment with \"quotes\" and [brackets]\n\
+             \"float radius\" [2.5]\"string name\" [ \"a # b\" ] -1e-2",
+        )
+        .unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Word("Shape".to_string()),
+                Token::String("sphere".to_string()),
+                Token::String("float radius".to_string()),
+                Token::Open,
+                Token::Number(2.5),
+                Token::Close,
+                Token::String("string name".to_string()),
+                Token::Open,
+                Token::String("a # b".to_string()),
+                Token::Close,
+                Token::Number(-0.01),
+            ]
+        );
+        assert!(tokenize("Shape \"sphere").is_err());
+        assert!(tokenize("Translate 1 2x 3").is_err());
+    }
+
+    #[test]
+    fn transform_is_column_major() {
+        let importer = run("Transform [1 0 0 0  0 1 0 0  0 0 1 0  1 2 3 1]").unwrap();
+        let transform = importer.attributes.transform;
+        assert_close(transform.point(&Vec3::zero()), Vec3::new(1.0, 2.0, 3.0));
+        assert!(run("Transform [1 0 0 0  0 1 0 0  0 0 1 0]").is_err());
+    }
+
+    #[test]
+    fn concat_transform_applies_first() {
+        // pbrt post-multiplies the current transformation, so the scale applies first.
+        let importer =
+            run("Translate 1 0 0 ConcatTransform [2 0 0 0  0 2 0 0  0 0 2 0  0 0 0 1]").unwrap();
+        let transform = importer.attributes.transform;
+        assert_close(
+            transform.point(&Vec3::new(1.0, 1.0, 0.0)),
+            Vec3::new(3.0, 2.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn look_at_maps_world_to_camera() {
+        let importer = run("LookAt 0 0 5  0 0 0  0 1 0").unwrap();
+        let transform = importer.attributes.transform;
+        assert_close(transform.point(&Vec3::new(0.0, 0.0, 5.0)), Vec3::zero());
+        // The target is straight ahead along +Z, up is +Y and right is +X.
+        assert_close(transform.point(&Vec3::zero()), Vec3::new(0.0, 0.0, 5.0));
+        assert_close(
+            transform.vector(&Vec3::new(0.0, 1.0, 0.0)),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        assert_close(
+            transform.vector(&Vec3::new(-1.0, 0.0, 0.0)),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        assert!(run("LookAt 0 0 5  0 0 5  0 1 0").is_err());
+        assert!(run("LookAt 0 0 5  0 0 0").is_err());
+    }
+
+    #[test]
+    fn attribute_and_transform_stacks() {
+        let importer = run("Translate 1 0 0 \
+             AttributeBegin Translate 0 1 0 TransformBegin Scale 2 2 2 TransformEnd \
+             ReverseOrientation AttributeEnd")
+        .unwrap();
+        assert_close(
+            importer.attributes.transform.point(&Vec3::zero()),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        assert!(!importer.attributes.reverse_orientation);
+
+        assert!(run("AttributeEnd").is_err());
+        assert!(run("TransformEnd").is_err());
+        assert!(run("AttributeBegin AttributeEnd AttributeEnd").is_err());
+        assert!(run("ObjectEnd").is_err());
+        assert!(run("ObjectBegin \"a\" ObjectBegin \"b\"").is_err());
+        assert!(run("ObjectInstance \"missing\"").is_err());
+        assert!(run("NamedMaterial \"missing\"").is_err());
+    }
+
+    #[test]
+    fn object_instances() {
+        let importer = run(
+            "ObjectBegin \"ball\" Translate 0 0 -5 Shape \"sphere\" \"float radius\" 0.5 ObjectEnd \
+             Translate 0 0 1 \
+             AttributeBegin Translate -2 0 0 ObjectInstance \"ball\" AttributeEnd \
+             AttributeBegin Translate 2 0 0 ObjectInstance \"ball\" AttributeEnd",
+        )
+        .unwrap();
+        // The transformation within the definition is restored, and the shape is only placed by
+        // its instances.
+        assert_close(
+            importer.attributes.transform.point(&Vec3::zero()),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+        assert_eq!(importer.collidables.len(), 2);
+
+        let hit = |x: f64| {
+            let ray = Ray::new(Vec3::new(x, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+            importer
+                .collidables
+                .iter()
+                .find_closest_collision(&ray, 1e-6, f64::INFINITY)
+                .map(|collision| collision.t)
+        };
+        assert!((hit(-2.0).unwrap() - 3.5).abs() < 1e-9);
+        assert!((hit(2.0).unwrap() - 3.5).abs() < 1e-9);
+        assert!(hit(0.0).is_none());
+    }
+
+    #[test]
+    fn roughness_remap_matches_pbrt() {
+        // Values of pbrt-v3's `TrowbridgeReitzDistribution::RoughnessToAlpha`.
+        for &(roughness, alpha) in &[(0.001, 0.0472695), (0.01, 0.138924), (0.1, 0.46176)] {
+            let perceptual = perceptual_roughness(roughness, true);
+            assert!((perceptual * perceptual - alpha).abs() < 1e-6);
+        }
+        // Alpha is clamped to at most one.
+        assert_eq!(perceptual_roughness(0.5, true), 1.0);
+        assert_eq!(perceptual_roughness(0.25, false), 0.5);
+    }
+
+    #[test]
+    fn scene() {
+        let scene = parse(
+            "LookAt 0 0 5  0 0 0  0 1 0 Camera \"perspective\" \"float fov\" [45] \
+             Film \"image\" \"integer xresolution\" [200] \"integer yresolution\" [100] \
+             WorldBegin \
+             AttributeBegin AreaLightSource \"diffuse\" \"rgb L\" [4 4 4] \
+             Translate 0 3 0 Shape \"sphere\" \"float radius\" 0.5 AttributeEnd \
+             Shape \"trianglemesh\" \"integer indices\" [0 1 2] \"point P\" [0 0 0  1 0 0  0 1 0] \
+             WorldEnd",
+            PathBuf::new(),
+        )
+        .unwrap();
+        assert_eq!((scene.width, scene.height), (200, 100));
+        assert_eq!(scene.world.area_lights().len(), 1);
+        assert!(scene.world.environment().sample().is_none());
+        assert!(parse("WorldBegin WorldEnd", PathBuf::new()).is_err());
+    }
+}
@@ -0,0 +1,461 @@
+//! Polygon File Format (PLY) mesh loader.
+//!
+//! Reads ASCII and binary (little and big endian) files, using vertex positions, normals,
+//! surface coordinates and colors, and faces triangulated as fans. Other elements and properties
+//! are skipped.
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::mesh::Mesh;
+use crate::texture::srgb_to_linear;
+use crate::{Material, Vec3};
+
+/// Construct an invalid data error.
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("PLY: {}", message))
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// Scalar property type.
+#[derive(Copy, Clone)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return Err(invalid(&format!("unknown property type `{}`", name))),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// Largest value of integer types, by which colors are normalized.
+    fn normalization(self) -> f64 {
+        match self {
+            Scalar::U8 => 255.0,
+            Scalar::U16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+struct Property {
+    name: String,
+    scalar: Scalar,
+    /// Type of the item count for list properties.
+    list: Option<Scalar>,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Reader of property values from the body of a file.
+struct Body<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Body<'_> {
+    fn read(&mut self, scalar: Scalar) -> Result<f64> {
+        if self.format == Format::Ascii {
+            let rest = &self.bytes[self.position..];
+            let start = rest
+                .iter()
+                .position(|b| !b.is_ascii_whitespace())
+                .ok_or_else(|| invalid("unexpected end of data"))?;
+            let length = rest[start..]
+                .iter()
+                .position(|b| b.is_ascii_whitespace())
+                .unwrap_or(rest.len() - start);
+            self.position += start + length;
+            let token = std::str::from_utf8(&rest[start..start + length])
+                .map_err(|_| invalid("invalid ASCII value"))?;
+            return token
+                .parse()
+                .map_err(|_| invalid(&format!("invalid value `{}`", token)));
+        }
+
+        let size = scalar.size();
+        let mut bytes = [0u8; 8];
+        bytes[..size].copy_from_slice(
+            self.bytes
+                .get(self.position..self.position + size)
+                .ok_or_else(|| invalid("unexpected end of data"))?,
+        );
+        self.position += size;
+        if self.format == Format::BinaryBigEndian {
+            bytes[..size].reverse();
+        }
+        let b = bytes;
+        Ok(match scalar {
+            Scalar::I8 => b[0] as i8 as f64,
+            Scalar::U8 => b[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(b),
+        })
+    }
+}
+
+/// Load a mesh from a PLY file.
+pub fn open<P: AsRef<Path>>(path: P, material: Rc<dyn Material>) -> Result<Mesh> {
+    parse(&fs::read(path)?, material)
+}
+
+/// Parse a mesh from the contents of a PLY file.
+///
+/// 8 and 16 bit vertex colors are converted from sRGB to linear values.
+pub fn parse(bytes: &[u8], material: Rc<dyn Material>) -> Result<Mesh> {
+    let (format, elements, body_start) = parse_header(bytes)?;
+    let mut body = Body {
+        format,
+        bytes: &bytes[body_start..],
+        position: 0,
+    };
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+
+    for element in &elements {
+        let find = |names: &[&str]| {
+            element
+                .properties
+                .iter()
+                .position(|p| names.contains(&p.name.as_str()))
+        };
+        let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+        let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+        let uv = [
+            find(&["u", "s", "texture_u", "texture_s"]),
+            find(&["v", "t", "texture_v", "texture_t"]),
+        ];
+        let color = [
+            find(&["red", "r", "diffuse_red"]),
+            find(&["green", "g", "diffuse_green"]),
+            find(&["blue", "b", "diffuse_blue"]),
+        ];
+        let face = find(&["vertex_indices", "vertex_index"]);
+
+        let mut values = vec![0.0; element.properties.len()];
+        for _ in 0..element.count {
+            let mut polygon = Vec::new();
+            for (i, property) in element.properties.iter().enumerate() {
+                match property.list {
+                    Some(count_scalar) => {
+                        let count = body.read(count_scalar)? as usize;
+                        for _ in 0..count {
+                            let value = body.read(property.scalar)?;
+                            if Some(i) == face {
+                                polygon.push(value as usize);
+                            }
+                        }
+                    }
+                    None => values[i] = body.read(property.scalar)?,
+                }
+            }
+
+            if element.name == "vertex" {
+                let get = |index: Option<usize>| index.map(|i| values[i]);
+                let vector = |indices: &[Option<usize>; 3]| {
+                    Some(Vec3::new(
+                        get(indices[0])?,
+                        get(indices[1])?,
+                        get(indices[2])?,
+                    ))
+                };
+                positions
+                    .push(vector(&position).ok_or_else(|| invalid("vertex without position"))?);
+                if let Some(n) = vector(&normal) {
+                    normals.push(n);
+                }
+                if let (Some(u), Some(v)) = (get(uv[0]), get(uv[1])) {
+                    uvs.push((u, v));
+                }
+                if let Some(c) = vector(&color) {
+                    let scale = element.properties[color[0].unwrap()].scalar.normalization();
+                    let c = c / scale;
+                    colors.push(if scale > 1.0 { srgb_to_linear(&c) } else { c });
+                }
+            } else if element.name == "face" {
+                for k in 1..polygon.len().saturating_sub(1) {
+                    indices.push([polygon[0], polygon[k], polygon[k + 1]]);
+                }
+            }
+        }
+    }
+
+    if indices.iter().flatten().any(|&i| i >= positions.len()) {
+        return Err(invalid("face vertex index out of range"));
+    }
+    let mut mesh = Mesh::new(positions, indices, material);
+    if !normals.is_empty() {
+        mesh.set_normals(normals);
+    }
+    if !uvs.is_empty() {
+        mesh.set_uvs(uvs);
+    }
+    if !colors.is_empty() {
+        mesh.set_colors(colors);
+    }
+    Ok(mesh)
+}
+
+/// Parse the header, returning the format, elements and offset of the body.
+fn parse_header(bytes: &[u8]) -> Result<(Format, Vec<Element>, usize)> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+    loop {
+        let length = bytes[offset..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| invalid("unterminated header"))?;
+        let line = std::str::from_utf8(&bytes[offset..offset + length])
+            .map_err(|_| invalid("invalid header"))?
+            .trim();
+        offset += length + 1;
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["ply"] | [] => {}
+            ["comment", ..] | ["obj_info", ..] => {}
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(invalid(&format!("unknown format `{}`", name))),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| invalid("invalid element count"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid("property before element"))?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    scalar: Scalar::parse(item)?,
+                    list: Some(Scalar::parse(count)?),
+                }),
+            ["property", scalar, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid("property before element"))?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    scalar: Scalar::parse(scalar)?,
+                    list: None,
+                }),
+            ["end_header"] => break,
+            _ => return Err(invalid(&format!("unexpected header line `{}`", line))),
+        }
+    }
+    if !bytes.starts_with(b"ply") {
+        return Err(invalid("missing magic number"));
+    }
+    Ok((
+        format.ok_or_else(|| invalid("missing format"))?,
+        elements,
+        offset,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambert;
+    use crate::Ray;
+
+    fn material() -> Rc<dyn Material> {
+        Rc::new(Lambert::new(Vec3::new(0.5, 0.5, 0.5)))
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).magnitude() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    /// Unit square in the xy plane as a single quad.
+    const SQUARE: [[f32; 3]; 4] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+
+    fn binary_square(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian {
+            "binary_big_endian"
+        } else {
+            "binary_little_endian"
+        };
+        let mut bytes = format!(
+            "ply\nformat {} 1.0\nelement vertex 4\nproperty float x\nproperty float y\n\
+             property float z\nelement face 1\nproperty list uchar int vertex_indices\n\
+             end_header\n",
+            format
+        )
+        .into_bytes();
+        for value in SQUARE.iter().flatten() {
+            bytes.extend_from_slice(&if big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            });
+        }
+        bytes.push(4);
+        for index in 0..4i32 {
+            bytes.extend_from_slice(&if big_endian {
+                index.to_be_bytes()
+            } else {
+                index.to_le_bytes()
+            });
+        }
+        bytes
+    }
+
+    #[test]
+    fn ascii_with_colors() {
+        let source = b"ply
+format ascii 1.0
+comment unit square
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 255 0 0
+1 1 0 255 0 0
+0 1 0 255 0 0
+4 0 1 2 3
+";
+        let mesh = Rc::new(parse(source, material()).unwrap());
+        assert_eq!(mesh.positions().len(), 4);
+        assert_close(mesh.positions()[2], Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(mesh.indices(), &[[0, 1, 2], [0, 2, 3]]);
+
+        let ray = Ray::new(Vec3::new(0.25, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let collision = mesh
+            .triangles()
+            .iter()
+            .find_map(|triangle| triangle.collision(&ray, 1e-6, f64::INFINITY))
+            .unwrap();
+        assert_close(collision.color.unwrap(), Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn polygons_are_triangulated_as_fans() {
+        let source = b"ply
+format ascii 1.0
+element vertex 5
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+2 1 0
+1 2 0
+0 1 0
+5 0 1 2 3 4
+";
+        let mesh = parse(source, material()).unwrap();
+        assert_eq!(mesh.indices(), &[[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+    }
+
+    #[test]
+    fn binary() {
+        for &big_endian in &[false, true] {
+            let mesh = parse(&binary_square(big_endian), material()).unwrap();
+            assert_eq!(mesh.positions().len(), 4);
+            for (position, expected) in mesh.positions().iter().zip(&SQUARE) {
+                let expected =
+                    Vec3::new(expected[0] as f64, expected[1] as f64, expected[2] as f64);
+                assert_close(*position, expected);
+            }
+            assert_eq!(mesh.indices(), &[[0, 1, 2], [0, 2, 3]]);
+        }
+    }
+
+    #[test]
+    fn truncated_binary_is_an_error() {
+        let bytes = binary_square(false);
+        let error = parse(&bytes[..bytes.len() - 1], material()).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn out_of_range_index_is_an_error() {
+        let source = b"ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+0 1 0
+3 0 1 3
+";
+        let error = parse(source, material()).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,271 @@
+//! Real roots of low degree polynomials.
+//!
+//! Polynomials are given by their coefficients from the highest degree down, and roots are
+//! returned in increasing order. Degenerate leading coefficients fall back to lower degrees.
+use std::f64::consts::PI;
+
+/// Newton iterations used to polish roots found in closed form.
+const POLISH_ITERATIONS: usize = 2;
+
+/// Real roots of `a x² + b x + c`.
+///
+/// Uses the form of the quadratic formula which avoids cancellation between `b` and the square
+/// root of the discriminant.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return if b == 0.0 { vec![] } else { vec![-c / b] };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0.0 {
+        return vec![0.0, 0.0];
+    }
+    let (x0, x1) = (q / a, c / q);
+    if x0 < x1 {
+        vec![x0, x1]
+    } else {
+        vec![x1, x0]
+    }
+}
+
+/// Real roots of `a x³ + b x² + c x + d`.
+///
+/// Uses the trigonometric method when there are three real roots and Cardano's formula otherwise.
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return solve_quadratic(b, c, d);
+    }
+    let (b, c, d) = (b / a, c / a, d / a);
+    let q = (b * b - 3.0 * c) / 9.0;
+    let r = (2.0 * b * b * b - 9.0 * b * c + 27.0 * d) / 54.0;
+    let q3 = q * q * q;
+    let shift = b / 3.0;
+
+    let mut roots = if r * r < q3 {
+        let theta = (r / q3.sqrt()).clamp(-1.0, 1.0).acos();
+        let scale = -2.0 * q.sqrt();
+        vec![
+            scale * (theta / 3.0).cos() - shift,
+            scale * ((theta + 2.0 * PI) / 3.0).cos() - shift,
+            scale * ((theta - 2.0 * PI) / 3.0).cos() - shift,
+        ]
+    } else {
+        let big_a = -r.signum() * (r.abs() + (r * r - q3).sqrt()).cbrt();
+        let big_b = if big_a == 0.0 { 0.0 } else { q / big_a };
+        if big_a != 0.0 && r * r - q3 <= 1e-12 * q3 {
+            // Repeated root, which rounding would otherwise lose.
+            vec![big_a + big_b - shift, -0.5 * (big_a + big_b) - shift]
+        } else {
+            vec![big_a + big_b - shift]
+        }
+    };
+    for root in roots.iter_mut() {
+        *root = polish(&[1.0, b, c, d], *root);
+    }
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
+    roots
+}
+
+/// Real roots of `a x⁴ + b x³ + c x² + d x + e`.
+///
+/// Uses Ferrari's method, factoring the depressed quartic into two quadratics through a root of
+/// its resolvent cubic.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return solve_cubic(b, c, d, e);
+    }
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+
+    // Depressed quartic y⁴ + p y² + q y + r with x = y - b / 4.
+    let b2 = b * b;
+    let p = c - 3.0 * b2 / 8.0;
+    let q = d - b * c / 2.0 + b2 * b / 8.0;
+    let r = e - b * d / 4.0 + b2 * c / 16.0 - 3.0 * b2 * b2 / 256.0;
+    let shift = b / 4.0;
+
+    let mut roots = Vec::with_capacity(4);
+    if q.abs() < 1e-12 {
+        // Biquadratic in y².
+        for z in solve_quadratic(1.0, p, r) {
+            if z >= 0.0 {
+                let y = z.sqrt();
+                roots.push(y - shift);
+                roots.push(-y - shift);
+            }
+        }
+    } else {
+        // Largest root of the resolvent cubic, which is positive when q is not zero.
+        let m = solve_cubic(1.0, p, p * p / 4.0 - r, -q * q / 8.0)
+            .into_iter()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if m <= 0.0 {
+            return vec![];
+        }
+        let s = (2.0 * m).sqrt();
+        let half = p / 2.0 + m;
+        for y in solve_quadratic(1.0, s, half - q / (2.0 * s))
+            .into_iter()
+            .chain(solve_quadratic(1.0, -s, half + q / (2.0 * s)))
+        {
+            roots.push(y - shift);
+        }
+    }
+    for root in roots.iter_mut() {
+        *root = polish(&[1.0, b, c, d, e], *root);
+    }
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
+    roots
+}
+
+/// Refine a root with Newton's method, keeping the original if an iteration does not improve it.
+fn polish(coefficients: &[f64], root: f64) -> f64 {
+    let mut root = root;
+    for _ in 0..POLISH_ITERATIONS {
+        let (value, derivative) = evaluate(coefficients, root);
+        if derivative == 0.0 {
+            break;
+        }
+        let next = root - value / derivative;
+        if evaluate(coefficients, next).0.abs() >= value.abs() {
+            break;
+        }
+        root = next;
+    }
+    root
+}
+
+/// Value and derivative of a polynomial at `x` (Horner's method).
+fn evaluate(coefficients: &[f64], x: f64) -> (f64, f64) {
+    coefficients
+        .iter()
+        .fold((0.0, 0.0), |(value, derivative), &c| {
+            (value * x + c, derivative * x + value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vec3;
+
+    fn assert_roots(roots: &[f64], expected: &[f64], tolerance: f64) {
+        assert_eq!(roots.len(), expected.len(), "roots {:?}", roots);
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!(
+                (root - expected).abs() < tolerance,
+                "roots {:?}, expected {:?}",
+                roots,
+                expected
+            );
+        }
+    }
+
+    /// Coefficients of a torus about the z axis crossed by a ray along x, as set up by `Torus`.
+    fn torus_ray(major: f64, minor: f64, origin: Vec3) -> [f64; 5] {
+        let d = Vec3::new(1.0, 0.0, 0.0);
+        let r2 = major * major;
+        let n = origin.dot(&d);
+        let k = origin.magnitude_squared() + r2 - minor * minor;
+        [
+            1.0,
+            4.0 * n,
+            4.0 * n * n + 2.0 * k - 4.0 * r2 * (d.x * d.x + d.y * d.y),
+            4.0 * n * k - 8.0 * r2 * (origin.x * d.x + origin.y * d.y),
+            k * k - 4.0 * r2 * (origin.x * origin.x + origin.y * origin.y),
+        ]
+    }
+
+    #[test]
+    fn quadratic_known_roots() {
+        assert_roots(&solve_quadratic(2.0, -2.0, -12.0), &[-2.0, 3.0], 1e-12);
+        assert_roots(&solve_quadratic(1.0, 0.0, 1.0), &[], 1e-12);
+        // Roots of very different magnitude do not cancel.
+        assert_roots(&solve_quadratic(1.0, -1e8, 1.0), &[1e-8, 1e8], 1e-16);
+    }
+
+    #[test]
+    fn quadratic_repeated_root() {
+        assert_roots(&solve_quadratic(1.0, -2.0, 1.0), &[1.0, 1.0], 1e-12);
+        assert_roots(&solve_quadratic(3.0, 0.0, 0.0), &[0.0, 0.0], 1e-12);
+    }
+
+    #[test]
+    fn quadratic_zero_leading_coefficient() {
+        assert_roots(&solve_quadratic(0.0, 2.0, -3.0), &[1.5], 1e-12);
+        assert_roots(&solve_quadratic(0.0, 0.0, 1.0), &[], 1e-12);
+    }
+
+    #[test]
+    fn cubic_known_roots() {
+        // (x + 1)(x - 2)(x - 5)
+        assert_roots(&solve_cubic(2.0, -12.0, 6.0, 20.0), &[-1.0, 2.0, 5.0], 1e-9);
+        // (x - 3)(x² + 1)
+        assert_roots(&solve_cubic(1.0, -3.0, 1.0, -3.0), &[3.0], 1e-9);
+    }
+
+    #[test]
+    fn cubic_repeated_roots() {
+        // (x - 2)³
+        for root in solve_cubic(1.0, -6.0, 12.0, -8.0) {
+            assert!((root - 2.0).abs() < 1e-5, "root {}", root);
+        }
+        // (x - 1)²(x - 3)
+        assert_roots(&solve_cubic(1.0, -5.0, 7.0, -3.0), &[1.0, 3.0], 1e-9);
+    }
+
+    #[test]
+    fn cubic_zero_leading_coefficient() {
+        assert_roots(&solve_cubic(0.0, 1.0, -3.0, 2.0), &[1.0, 2.0], 1e-12);
+    }
+
+    #[test]
+    fn quartic_known_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(
+            &solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0),
+            &[1.0, 2.0, 3.0, 4.0],
+            1e-9,
+        );
+        // x⁴ + 1
+        assert_roots(&solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0), &[], 1e-12);
+    }
+
+    #[test]
+    fn quartic_repeated_roots() {
+        // (x - 1)²(x + 2)²
+        let roots = solve_quartic(1.0, 2.0, -3.0, -4.0, 4.0);
+        assert!(!roots.is_empty());
+        for root in roots {
+            assert!(
+                (root - 1.0).abs() < 1e-6 || (root + 2.0).abs() < 1e-6,
+                "root {}",
+                root
+            );
+        }
+    }
+
+    #[test]
+    fn quartic_zero_leading_coefficient() {
+        assert_roots(
+            &solve_quartic(0.0, 1.0, -6.0, 11.0, -6.0),
+            &[1.0, 2.0, 3.0],
+            1e-9,
+        );
+    }
+
+    #[test]
+    fn quartic_torus() {
+        // Crossing the tube through its center circle.
+        let [a, b, c, d, e] = torus_ray(2.0, 0.5, Vec3::new(-5.0, 0.0, 0.0));
+        assert_roots(&solve_quartic(a, b, c, d, e), &[2.5, 3.5, 6.5, 7.5], 1e-9);
+        // Crossing the tube off center, |x| = 2 ± √(0.5² - 0.3²).
+        let [a, b, c, d, e] = torus_ray(2.0, 0.5, Vec3::new(-5.0, 0.0, 0.3));
+        assert_roots(&solve_quartic(a, b, c, d, e), &[2.6, 3.4, 6.6, 7.4], 1e-9);
+        // Passing over the tube.
+        let [a, b, c, d, e] = torus_ray(2.0, 0.5, Vec3::new(-5.0, 0.0, 0.6));
+        assert_roots(&solve_quartic(a, b, c, d, e), &[], 1e-12);
+    }
+}
@@ -0,0 +1,308 @@
+//! Principled (Disney-style) uber material.
+//!
+//! Based on "Physically Based Shading at Disney" (Burley 2012), combining diffuse, sheen,
+//! specular, clearcoat and transmission lobes controlled by artist friendly parameters.
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use rand::Rng;
+
+use crate::microfacet::{
+    evaluate_dielectric, ggx_d, roughness_to_alpha, sample_dielectric, sample_visible_normal,
+    smith_g2, visible_normal_pdf, Frame,
+};
+use crate::{Collision, Material, Ray, Texture, Vec3};
+
+/// Schlick's Fresnel weight `(1 - cos)^5`.
+fn schlick_weight(cosine: f64) -> f64 {
+    (1.0 - cosine).clamp(0.0, 1.0).powi(5)
+}
+
+/// Linear interpolation between two vectors.
+fn lerp(a: Vec3, b: Vec3, t: f64) -> Vec3 {
+    a * (1.0 - t) + b * t
+}
+
+/// Generalized Trowbridge-Reitz distribution (γ = 1) of clearcoat microfacet normals.
+fn gtr1_d(m: &Vec3, alpha: f64) -> f64 {
+    if m.z <= 0.0 {
+        return 0.0;
+    }
+    if alpha >= 1.0 {
+        return 1.0 / PI;
+    }
+    let a2 = alpha * alpha;
+    (a2 - 1.0) / (PI * a2.ln() * (1.0 + (a2 - 1.0) * m.z * m.z))
+}
+
+/// Sample a clearcoat microfacet normal proportional to `gtr1_d` times its cosine.
+fn sample_gtr1(alpha: f64, u1: f64, u2: f64) -> Vec3 {
+    let a2 = alpha * alpha;
+    let cos_theta = ((1.0 - a2.powf(1.0 - u1)) / (1.0 - a2)).max(0.0).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+    Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+/// Material parameters evaluated at a collision.
+struct Parameters {
+    base_color: Vec3,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    sheen: f64,
+    sheen_tint: f64,
+    clearcoat: f64,
+    clearcoat_gloss: f64,
+    transmission: f64,
+}
+
+impl Parameters {
+    /// Sampling probabilities of the diffuse, specular, clearcoat and transmission lobes.
+    fn lobe_probabilities(&self) -> [f64; 4] {
+        let diffuse = (1.0 - self.metallic) * (1.0 - self.transmission);
+        let transmission = (1.0 - self.metallic) * self.transmission;
+        let specular = 1.0 - transmission;
+        let clearcoat = 0.25 * self.clearcoat;
+        let total = diffuse + specular + clearcoat + transmission;
+        [
+            diffuse / total,
+            specular / total,
+            clearcoat / total,
+            transmission / total,
+        ]
+    }
+
+    fn alpha(&self) -> f64 {
+        roughness_to_alpha(self.roughness)
+    }
+
+    fn clearcoat_alpha(&self) -> f64 {
+        0.1 * (1.0 - self.clearcoat_gloss) + 0.001 * self.clearcoat_gloss
+    }
+}
+
+/// Principled uber material.
+///
+/// Every parameter is a texture, so constant values are given as `Rc::new(Vec3)` for colors or
+/// `Rc::new(f64)` for scalars. Scalar parameters are in [0.0, 1.0].
+pub struct Principled {
+    base_color: Rc<dyn Texture>,
+    metallic: Rc<dyn Texture>,
+    roughness: Rc<dyn Texture>,
+    specular: Rc<dyn Texture>,
+    sheen: Rc<dyn Texture>,
+    sheen_tint: Rc<dyn Texture>,
+    clearcoat: Rc<dyn Texture>,
+    clearcoat_gloss: Rc<dyn Texture>,
+    transmission: Rc<dyn Texture>,
+    refraction_index: f64,
+}
+
+impl Principled {
+    /// Construct a dielectric, moderately rough principled material.
+    pub fn new(base_color: Rc<dyn Texture>) -> Self {
+        Self {
+            base_color,
+            metallic: Rc::new(0.0),
+            roughness: Rc::new(0.5),
+            specular: Rc::new(0.5),
+            sheen: Rc::new(0.0),
+            sheen_tint: Rc::new(0.5),
+            clearcoat: Rc::new(0.0),
+            clearcoat_gloss: Rc::new(1.0),
+            transmission: Rc::new(0.0),
+            refraction_index: 1.5,
+        }
+    }
+
+    pub fn set_base_color(&mut self, base_color: Rc<dyn Texture>) -> &mut Self {
+        self.base_color = base_color;
+        self
+    }
+
+    /// Set the blend between dielectric (0.0) and metallic (1.0) behavior.
+    pub fn set_metallic(&mut self, metallic: Rc<dyn Texture>) -> &mut Self {
+        self.metallic = metallic;
+        self
+    }
+
+    /// Set the perceptual roughness of the specular and transmission lobes.
+    pub fn set_roughness(&mut self, roughness: Rc<dyn Texture>) -> &mut Self {
+        self.roughness = roughness;
+        self
+    }
+
+    /// Set the dielectric specular amount (0.5 corresponds to 4% normal incidence reflectance).
+    pub fn set_specular(&mut self, specular: Rc<dyn Texture>) -> &mut Self {
+        self.specular = specular;
+        self
+    }
+
+    /// Set the amount of grazing retro-reflection, e.g. for cloth.
+    pub fn set_sheen(&mut self, sheen: Rc<dyn Texture>) -> &mut Self {
+        self.sheen = sheen;
+        self
+    }
+
+    /// Set the blend of the sheen color from white towards the base color.
+    pub fn set_sheen_tint(&mut self, sheen_tint: Rc<dyn Texture>) -> &mut Self {
+        self.sheen_tint = sheen_tint;
+        self
+    }
+
+    /// Set the amount of the secondary clear specular layer.
+    pub fn set_clearcoat(&mut self, clearcoat: Rc<dyn Texture>) -> &mut Self {
+        self.clearcoat = clearcoat;
+        self
+    }
+
+    /// Set the glossiness of the clearcoat layer (0.0 satin to 1.0 gloss).
+    pub fn set_clearcoat_gloss(&mut self, clearcoat_gloss: Rc<dyn Texture>) -> &mut Self {
+        self.clearcoat_gloss = clearcoat_gloss;
+        self
+    }
+
+    /// Set the fraction of the dielectric base which transmits light like glass.
+    pub fn set_transmission(&mut self, transmission: Rc<dyn Texture>) -> &mut Self {
+        self.transmission = transmission;
+        self
+    }
+
+    /// Set the index of refraction of the transmission lobe.
+    pub fn set_refraction_index(&mut self, refraction_index: f64) -> &mut Self {
+        self.refraction_index = refraction_index;
+        self
+    }
+
+    fn parameters(&self, collision: &Collision) -> Parameters {
+        let scalar = |texture: &Rc<dyn Texture>| texture.value(collision).x.clamp(0.0, 1.0);
+        Parameters {
+            base_color: self.base_color.value(collision),
+            metallic: scalar(&self.metallic),
+            roughness: scalar(&self.roughness),
+            specular: scalar(&self.specular),
+            sheen: scalar(&self.sheen),
+            sheen_tint: scalar(&self.sheen_tint),
+            clearcoat: scalar(&self.clearcoat),
+            clearcoat_gloss: scalar(&self.clearcoat_gloss),
+            transmission: scalar(&self.transmission),
+        }
+    }
+
+    /// Ratio of the refractive index of the far side of the surface to the near side.
+    fn eta(&self, collision: &Collision) -> f64 {
+        if collision.front_face {
+            self.refraction_index
+        } else {
+            1.0 / self.refraction_index
+        }
+    }
+
+    /// Scattering function times cosine, and sampling density, for local directions.
+    fn evaluate_local(&self, p: &Parameters, wo: &Vec3, wi: &Vec3, eta: f64) -> (Vec3, f64) {
+        if wo.z <= 0.0 || wi.z == 0.0 {
+            return (Vec3::zero(), 0.0);
+        }
+        let [diffuse_p, specular_p, clearcoat_p, transmission_p] = p.lobe_probabilities();
+        let dielectric_weight = (1.0 - p.metallic) * (1.0 - p.transmission);
+        let transmission_weight = (1.0 - p.metallic) * p.transmission;
+
+        // Glass-like reflection and transmission, with transmitted light tinted by the base color.
+        let (dielectric, dielectric_pdf) = evaluate_dielectric(wo, wi, eta, p.alpha());
+        let transmission_tint = if wi.z < 0.0 {
+            p.base_color
+        } else {
+            Vec3::one()
+        };
+        let mut value = transmission_tint * (transmission_weight * dielectric);
+        let mut pdf = transmission_p * dielectric_pdf;
+        if wi.z < 0.0 {
+            return (value, pdf);
+        }
+
+        let m = (*wo + *wi).normalized();
+        let cos_d = wi.dot(&m);
+
+        // Diffuse with grazing retro-reflection, and sheen.
+        let fd90 = 0.5 + 2.0 * p.roughness * cos_d * cos_d;
+        let fd = (1.0 + (fd90 - 1.0) * schlick_weight(wi.z))
+            * (1.0 + (fd90 - 1.0) * schlick_weight(wo.z));
+        let luminance = p.base_color.luminance();
+        let tint = if luminance > 0.0 {
+            p.base_color / luminance
+        } else {
+            Vec3::one()
+        };
+        let sheen = lerp(Vec3::one(), tint, p.sheen_tint) * (p.sheen * schlick_weight(cos_d));
+        value += (p.base_color * (fd / PI) + sheen) * (dielectric_weight * wi.z);
+        pdf += diffuse_p * wi.z / PI;
+
+        // Specular reflection, blending from dielectric to metallic reflectance.
+        let f0 = lerp(Vec3::one() * (0.08 * p.specular), p.base_color, p.metallic);
+        let fresnel = lerp(f0, Vec3::one(), schlick_weight(cos_d));
+        let alpha = p.alpha();
+        let specular = ggx_d(&m, alpha) * smith_g2(wo, wi, alpha) / (4.0 * wo.z);
+        value += fresnel * ((1.0 - transmission_weight) * specular);
+        pdf += specular_p * visible_normal_pdf(wo, &m, alpha) / (4.0 * wo.dot(&m));
+
+        // Clearcoat.
+        let clearcoat_alpha = p.clearcoat_alpha();
+        let d = gtr1_d(&m, clearcoat_alpha);
+        let fresnel = 0.04 + 0.96 * schlick_weight(cos_d);
+        let clearcoat = 0.25 * p.clearcoat * fresnel * d * smith_g2(wo, wi, 0.25) / (4.0 * wo.z);
+        value += Vec3::one() * clearcoat;
+        pdf += clearcoat_p * d * m.z / (4.0 * wo.dot(&m));
+
+        (value, pdf)
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3)> {
+        let mut rng = rand::thread_rng();
+        let p = self.parameters(collision);
+        let frame = Frame::new(collision.normal);
+        let wo = frame.to_local(&-ray_in.direction.normalized());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let eta = self.eta(collision);
+
+        let [diffuse_p, specular_p, clearcoat_p, _] = p.lobe_probabilities();
+        let choice = rng.gen::<f64>();
+        let wi = if choice < diffuse_p {
+            (Vec3::new(0.0, 0.0, 1.0) + Vec3::random_unit_vector()).normalized()
+        } else if choice < diffuse_p + specular_p {
+            let m = sample_visible_normal(&wo, p.alpha(), rng.gen(), rng.gen());
+            (-wo).reflect(m)
+        } else if choice < diffuse_p + specular_p + clearcoat_p {
+            let m = sample_gtr1(p.clearcoat_alpha(), rng.gen(), rng.gen());
+            (-wo).reflect(m)
+        } else {
+            sample_dielectric(&wo, eta, p.alpha(), rng.gen(), rng.gen(), rng.gen())?
+        };
+
+        let (value, pdf) = self.evaluate_local(&p, &wo, &wi, eta);
+        if pdf <= 0.0 || !pdf.is_finite() {
+            return None;
+        }
+        Some((Ray::new(collision.point, frame.to_world(&wi)), value / pdf))
+    }
+
+    fn evaluate(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> Vec3 {
+        let frame = Frame::new(collision.normal);
+        let wo = frame.to_local(&-ray_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        let p = self.parameters(collision);
+        self.evaluate_local(&p, &wo, &wi, self.eta(collision)).0
+    }
+
+    fn pdf(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> f64 {
+        let frame = Frame::new(collision.normal);
+        let wo = frame.to_local(&-ray_in.direction.normalized());
+        let wi = frame.to_local(&direction.normalized());
+        let p = self.parameters(collision);
+        self.evaluate_local(&p, &wo, &wi, self.eta(collision)).1
+    }
+}
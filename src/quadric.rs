@@ -0,0 +1,356 @@
+//! Collidable cylinders, cones and capsules.
+//!
+//! Shapes are defined about an axis segment and intersected in a local frame with the axis along
+//! +Z, starting from the base at the origin.
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use crate::aabb::Aabb;
+use crate::collide::Interval;
+use crate::microfacet::Frame;
+use crate::polynomial::solve_quadratic;
+use crate::{Collidable, Collision, Material, Ray, Vec3};
+
+/// Surface coordinates about the local Z axis, with `u` increasing counter-clockwise from +X.
+fn angle_u(p: &Vec3) -> f64 {
+    let phi = p.y.atan2(p.x);
+    (if phi < 0.0 { phi + 2.0 * PI } else { phi }) / (2.0 * PI)
+}
+
+/// Collision found in the local frame of a shape.
+struct Hit {
+    t: f64,
+    normal: Vec3,
+    uv: (f64, f64),
+    tangent: Vec3,
+}
+
+/// Local frame of an axis segment from `base` to `top`.
+struct Axis {
+    base: Vec3,
+    frame: Frame,
+    height: f64,
+}
+
+impl Axis {
+    fn new(base: Vec3, top: Vec3) -> Self {
+        let axis = top - base;
+        Self {
+            base,
+            frame: Frame::new(axis.normalized()),
+            height: axis.magnitude(),
+        }
+    }
+
+    /// Ray origin and direction in the local frame.
+    fn to_local(&self, ray: &Ray) -> (Vec3, Vec3) {
+        (
+            self.frame.to_local(&(ray.point - self.base)),
+            self.frame.to_local(&ray.direction),
+        )
+    }
+
+    /// World space collision record for a local hit.
+    fn collision(&self, ray: &Ray, hit: &Hit, material: &Rc<dyn Material>) -> Collision {
+        Collision::new(
+            ray,
+            hit.t,
+            ray.at(hit.t),
+            self.frame.to_world(&hit.normal).normalized(),
+            hit.uv,
+            self.frame.to_world(&hit.tangent),
+            material.clone(),
+        )
+    }
+
+    /// World space collision for the closest local hit within bounds.
+    fn closest(
+        &self,
+        ray: &Ray,
+        hits: Vec<Hit>,
+        t_min: f64,
+        t_max: f64,
+        material: &Rc<dyn Material>,
+    ) -> Option<Collision> {
+        let hit = hits
+            .into_iter()
+            .filter(|hit| hit.t > t_min && hit.t < t_max)
+            .min_by(|x, y| x.t.partial_cmp(&y.t).unwrap_or(std::cmp::Ordering::Greater))?;
+        Some(self.collision(ray, &hit, material))
+    }
+
+    /// Spans inside a closed shape from all of its local hits.
+    fn intervals(&self, ray: &Ray, hits: Vec<Hit>, material: &Rc<dyn Material>) -> Vec<Interval> {
+        Interval::pairs(
+            hits.iter()
+                .map(|hit| self.collision(ray, hit, material))
+                .collect(),
+        )
+    }
+}
+
+/// Hits with a flat cap at height `z`, facing along `normal_z`.
+fn cap_hits(o: &Vec3, d: &Vec3, z: f64, radius: f64, normal_z: f64, hits: &mut Vec<Hit>) {
+    if d.z == 0.0 || radius <= 0.0 {
+        return;
+    }
+    let t = (z - o.z) / d.z;
+    let p = o + d * t;
+    if p.x * p.x + p.y * p.y <= radius * radius {
+        hits.push(Hit {
+            t,
+            normal: Vec3::new(0.0, 0.0, normal_z),
+            uv: (0.5 + p.x / (2.0 * radius), 0.5 + p.y / (2.0 * radius)),
+            tangent: Vec3::new(1.0, 0.0, 0.0),
+        });
+    }
+}
+
+/// Shared cone frustum, with linearly varying radius along its axis.
+struct Frustum {
+    axis: Axis,
+    base_radius: f64,
+    top_radius: f64,
+    capped: bool,
+    material: Rc<dyn Material>,
+}
+
+impl Frustum {
+    /// All hits with the (infinitely extended) surface, clipped to the axis segment.
+    fn hits(&self, ray: &Ray) -> Vec<Hit> {
+        let (o, d) = self.axis.to_local(ray);
+        let h = self.axis.height;
+        let r0 = self.base_radius;
+        let k = (self.top_radius - self.base_radius) / h;
+        let mut hits = Vec::with_capacity(4);
+
+        // Lateral surface: x² + y² = (r0 + k z)².
+        let ro = r0 + k * o.z;
+        let a = d.x * d.x + d.y * d.y - k * k * d.z * d.z;
+        let b = 2.0 * (o.x * d.x + o.y * d.y - k * ro * d.z);
+        let c = o.x * o.x + o.y * o.y - ro * ro;
+        for t in solve_quadratic(a, b, c) {
+            let p = o + d * t;
+            if p.z >= 0.0 && p.z <= h && r0 + k * p.z >= 0.0 {
+                hits.push(Hit {
+                    t,
+                    normal: Vec3::new(p.x, p.y, -k * (r0 + k * p.z)),
+                    uv: (angle_u(&p), p.z / h),
+                    tangent: Vec3::new(-p.y, p.x, 0.0),
+                });
+            }
+        }
+
+        if self.capped {
+            cap_hits(&o, &d, 0.0, self.base_radius, -1.0, &mut hits);
+            cap_hits(&o, &d, h, self.top_radius, 1.0, &mut hits);
+        }
+        hits
+    }
+
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        self.axis
+            .closest(ray, self.hits(ray), t_min, t_max, &self.material)
+    }
+
+    /// Spans inside the frustum, which is only closed if capped.
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval>> {
+        if self.capped {
+            Some(self.axis.intervals(ray, self.hits(ray), &self.material))
+        } else {
+            None
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let n = self.axis.frame.n;
+        let top = self.axis.base + n * self.axis.height;
+        Aabb::disk(self.axis.base, n, self.base_radius)
+            .union(&Aabb::disk(top, n, self.top_radius))
+    }
+}
+
+/// Collidable finite cylinder, optionally closed by flat caps.
+///
+/// Surface coordinates wrap `u` around the axis and run `v` from base to top; caps map the square
+/// enclosing them to [0.0, 1.0].
+pub struct Cylinder {
+    frustum: Frustum,
+}
+
+impl Cylinder {
+    /// Construct a cylinder.
+    ///
+    /// * `base` - Center of the base.
+    /// * `top` - Center of the top.
+    /// * `radius` - Radius of the cylinder.
+    /// * `capped` - If the ends are closed by caps.
+    pub fn new(
+        base: Vec3,
+        top: Vec3,
+        radius: f64,
+        capped: bool,
+        material: Rc<dyn Material>,
+    ) -> Self {
+        Self {
+            frustum: Frustum {
+                axis: Axis::new(base, top),
+                base_radius: radius,
+                top_radius: radius,
+                capped,
+                material,
+            },
+        }
+    }
+}
+
+impl Collidable for Cylinder {
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        self.frustum.collision(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.frustum.bounding_box())
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval>> {
+        self.frustum.intervals(ray)
+    }
+}
+
+/// Collidable cone, or truncated cone if the top radius is not zero, optionally closed by flat
+/// caps.
+///
+/// Surface coordinates are as for `Cylinder`.
+pub struct Cone {
+    frustum: Frustum,
+}
+
+impl Cone {
+    /// Construct a cone.
+    ///
+    /// * `base` - Center of the base.
+    /// * `top` - Center of the top (the apex if `top_radius` is zero).
+    /// * `base_radius` - Radius at the base.
+    /// * `top_radius` - Radius at the top.
+    /// * `capped` - If the ends are closed by caps.
+    pub fn new(
+        base: Vec3,
+        top: Vec3,
+        base_radius: f64,
+        top_radius: f64,
+        capped: bool,
+        material: Rc<dyn Material>,
+    ) -> Self {
+        Self {
+            frustum: Frustum {
+                axis: Axis::new(base, top),
+                base_radius,
+                top_radius,
+                capped,
+                material,
+            },
+        }
+    }
+}
+
+impl Collidable for Cone {
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        self.frustum.collision(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.frustum.bounding_box())
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval>> {
+        self.frustum.intervals(ray)
+    }
+}
+
+/// Collidable capsule: a cylinder closed by hemispheres.
+///
+/// Surface coordinates wrap `u` around the axis and run `v` along the full length from the bottom
+/// of the base hemisphere to the top of the top hemisphere.
+pub struct Capsule {
+    axis: Axis,
+    radius: f64,
+    material: Rc<dyn Material>,
+}
+
+impl Capsule {
+    /// Construct a capsule.
+    ///
+    /// * `base` - Center of the base hemisphere.
+    /// * `top` - Center of the top hemisphere.
+    /// * `radius` - Radius of the capsule.
+    pub fn new(base: Vec3, top: Vec3, radius: f64, material: Rc<dyn Material>) -> Self {
+        Self {
+            axis: Axis::new(base, top),
+            radius,
+            material,
+        }
+    }
+
+    /// All hits with the capsule surface.
+    fn hits(&self, ray: &Ray) -> Vec<Hit> {
+        let (o, d) = self.axis.to_local(ray);
+        let h = self.axis.height;
+        let r = self.radius;
+        let length = h + 2.0 * r;
+        let mut hits = Vec::with_capacity(4);
+        let mut push = |t: f64, center_z: f64| {
+            let p = o + d * t;
+            hits.push(Hit {
+                t,
+                normal: Vec3::new(p.x, p.y, p.z - center_z),
+                uv: (angle_u(&p), (p.z + r) / length),
+                tangent: Vec3::new(-p.y, p.x, 0.0),
+            });
+        };
+
+        // Cylinder between the hemisphere centers.
+        let a = d.x * d.x + d.y * d.y;
+        let b = 2.0 * (o.x * d.x + o.y * d.y);
+        let c = o.x * o.x + o.y * o.y - r * r;
+        if a > 0.0 {
+            for t in solve_quadratic(a, b, c) {
+                let z = o.z + d.z * t;
+                if z >= 0.0 && z <= h {
+                    push(t, z);
+                }
+            }
+        }
+
+        // Hemispheres beyond either end.
+        for &(center_z, below) in &[(0.0, true), (h, false)] {
+            let oc = Vec3::new(o.x, o.y, o.z - center_z);
+            let a = d.magnitude_squared();
+            let b = 2.0 * oc.dot(&d);
+            let c = oc.magnitude_squared() - r * r;
+            for t in solve_quadratic(a, b, c) {
+                let z = o.z + d.z * t;
+                if (below && z < 0.0) || (!below && z > h) {
+                    push(t, center_z);
+                }
+            }
+        }
+        hits
+    }
+}
+
+impl Collidable for Capsule {
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        self.axis
+            .closest(ray, self.hits(ray), t_min, t_max, &self.material)
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval>> {
+        Some(self.axis.intervals(ray, self.hits(ray), &self.material))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let top = self.axis.base + self.axis.frame.n * self.axis.height;
+        Some(Aabb::sphere(self.axis.base, self.radius).union(&Aabb::sphere(top, self.radius)))
+    }
+}
@@ -1,5 +1,6 @@
 //! Geometric ray which accrues color from collidable objects.
-use crate::{Collidable, FindCollision, Vec3};
+use crate::sampling::power_heuristic;
+use crate::{Vec3, World};
 
 /// Geometric ray which accrues color from collidable objects.
 ///
@@ -30,32 +31,98 @@ impl Ray {
     /// By reflecting off of collidable objects the color becomes a composite of the multiple
     /// materials it observes.
     ///
-    /// * `t_min` - Lower bound on the distance at which collisions are considered.
-    /// * `t_max` - Upper bound on the distance at which collisions are considered.
-    /// * `max_depth` - Bound on the number of recursive reflections.
-    pub fn color(
-        &self,
-        sky: &Vec3,
-        world: &Vec<Box<dyn Collidable>>,
-        t_min: f64,
-        t_max: f64,
-        max_depth: usize,
-    ) -> Vec3 {
-        if max_depth == 0 {
-            Vec3::zero()
-        } else {
-            if let Some(coll) = world.iter().find_closest_collision(self, t_min, t_max) {
-                if let Some((scattered, att)) = coll.material.scatter(self, &coll)
-                {
-                    let color = scattered.color(&sky, &world, t_min, t_max, max_depth - 1);
-                    att.hadamard_product(&color)
+    /// Light emitted by materials is gathered at each collision, and delta lights are sampled
+    /// through shadow rays. Area lights and the environment are also sampled directly, with the
+    /// contributions of direct rays and of scattered rays which find the same light combined by
+    /// multiple importance sampling.
+    ///
+    /// * `world` - World of collidables and lights.
+    /// * `max_depth` - Bound on the number of reflections.
+    pub fn color(&self, world: &World, max_depth: usize) -> Vec3 {
+        let environment = world.environment();
+        let mut color = Vec3::zero();
+        let mut throughput = Vec3::one();
+        let mut ray = Ray::new(self.point, self.direction);
+        // Density with which the current ray was scattered, zero for camera and specular rays.
+        let mut scatter_pdf = 0.0;
+
+        for _ in 0..max_depth {
+            let coll = match world.closest_collision(&ray) {
+                Some(coll) => coll,
+                None => {
+                    let weight = if scatter_pdf > 0.0 {
+                        power_heuristic(scatter_pdf, environment.pdf(&ray.direction))
+                    } else {
+                        1.0
+                    };
+                    let light = environment.color(&ray.direction) * weight;
+                    return color + throughput.hadamard_product(&light);
+                }
+            };
+
+            let emitted = coll.material.emitted(&ray, &coll);
+            if emitted.magnitude_squared() > 0.0 {
+                let weight = if scatter_pdf > 0.0 {
+                    power_heuristic(scatter_pdf, world.area_light_pdf(&ray.point, &coll))
                 } else {
-                    Vec3::zero()
+                    1.0
+                };
+                color += throughput.hadamard_product(&(emitted * weight));
+            }
+
+            for light in world.lights() {
+                if let Some(sample) = light.sample(&coll.point) {
+                    let bsdf = coll.material.evaluate(&ray, &coll, &sample.direction);
+                    if bsdf.magnitude_squared() > 0.0
+                        && !world.occluded(&Ray::new(coll.point, sample.direction), sample.distance)
+                    {
+                        let light = bsdf.hadamard_product(&sample.radiance);
+                        color += throughput.hadamard_product(&light);
+                    }
+                }
+            }
+
+            for light in world.area_lights() {
+                if let Some((sample, light_pdf)) = light.sample(&coll.point) {
+                    let bsdf = coll.material.evaluate(&ray, &coll, &sample.direction);
+                    if bsdf.magnitude_squared() > 0.0
+                        && !world.occluded(&Ray::new(coll.point, sample.direction), sample.distance)
+                    {
+                        let weight = power_heuristic(
+                            light_pdf,
+                            coll.material.pdf(&ray, &coll, &sample.direction),
+                        );
+                        let light = sample.radiance * (weight / light_pdf);
+                        color += throughput.hadamard_product(&bsdf.hadamard_product(&light));
+                    }
+                }
+            }
+
+            if let Some((direction, light_pdf)) = environment.sample() {
+                let bsdf = coll.material.evaluate(&ray, &coll, &direction);
+                if bsdf.magnitude_squared() > 0.0
+                    && !world.occluded(&Ray::new(coll.point, direction), f64::INFINITY)
+                {
+                    let weight =
+                        power_heuristic(light_pdf, coll.material.pdf(&ray, &coll, &direction));
+                    let light = environment.color(&direction) * (weight / light_pdf);
+                    color += throughput.hadamard_product(&bsdf.hadamard_product(&light));
+                }
+            }
+
+            match coll.material.scatter_lobe(&ray, &coll) {
+                Some((scattered, att, specular)) => {
+                    scatter_pdf = if specular {
+                        0.0
+                    } else {
+                        coll.material.pdf(&ray, &coll, &scattered.direction)
+                    };
+                    throughput = throughput.hadamard_product(&att);
+                    ray = scattered;
                 }
-            } else {
-                let t = (self.direction.normalized().y + 1.0) * 0.5;
-                Vec3::one() * (1.0 - t) + (*sky) * t
+                None => return color,
             }
         }
+        color
     }
 }
@@ -0,0 +1,181 @@
+//! Image rendering with optional crop windows.
+use std::fs::File;
+use std::io::{Result, Write as IoWrite};
+use std::path::Path;
+
+use itertools::iproduct;
+use rand::RngCore;
+
+use crate::{Camera, Color, World};
+
+/// Rectangular window of an image to render.
+#[derive(Copy, Clone, Debug)]
+pub enum CropWindow {
+    /// Window in pixel coordinates, measured from the top left of the image.
+    Pixels {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    },
+    /// Window in normalized [0.0, 1.0] coordinates, measured from the top left of the image.
+    Normalized {
+        x_min: f64,
+        y_min: f64,
+        x_max: f64,
+        y_max: f64,
+    },
+}
+
+impl CropWindow {
+    /// Pixel bounds `(x_min, y_min, x_max, y_max)` (maxima exclusive) clamped to an image size.
+    pub fn bounds(&self, image_width: usize, image_height: usize) -> (usize, usize, usize, usize) {
+        let (x_min, y_min, x_max, y_max) = match *self {
+            CropWindow::Pixels {
+                x,
+                y,
+                width,
+                height,
+            } => (x, y, x + width, y + height),
+            CropWindow::Normalized {
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+            } => (
+                (x_min * image_width as f64).floor() as usize,
+                (y_min * image_height as f64).floor() as usize,
+                (x_max * image_width as f64).ceil() as usize,
+                (y_max * image_height as f64).ceil() as usize,
+            ),
+        };
+        let x_max = x_max.min(image_width);
+        let y_max = y_max.min(image_height);
+        (x_min.min(x_max), y_min.min(y_max), x_max, y_max)
+    }
+}
+
+/// Output image layout when rendering a crop window.
+#[derive(Copy, Clone, Debug)]
+pub enum CropOutput {
+    /// Output only the crop window.
+    Cropped,
+    /// Output the full image with pixels outside the crop window left black.
+    FullFrame,
+}
+
+/// Rendered image, stored row by row from the top.
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    /// Construct a black image.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::BLACK; width * height],
+        }
+    }
+
+    /// Write the image as a plain text PPM file.
+    pub fn write_ppm<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut file = File::create(path)?;
+        writeln!(file, "P3\n{} {}\n255", self.width, self.height)?;
+        for color in &self.pixels {
+            writeln!(file, "{}", color)?;
+        }
+        Ok(())
+    }
+}
+
+/// Image renderer.
+///
+/// Pixels are rendered in square tiles covering the crop window (or the whole image).
+pub struct Renderer {
+    width: usize,
+    height: usize,
+    crop: Option<CropWindow>,
+    crop_output: CropOutput,
+    tile_size: usize,
+}
+
+impl Renderer {
+    /// Construct a renderer for an image size.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            crop: None,
+            crop_output: CropOutput::Cropped,
+            tile_size: 32,
+        }
+    }
+
+    /// Restrict rendering to a window of the image.
+    pub fn set_crop(&mut self, crop: CropWindow) -> &mut Self {
+        self.crop = Some(crop);
+        self
+    }
+
+    pub fn set_crop_output(&mut self, crop_output: CropOutput) -> &mut Self {
+        self.crop_output = crop_output;
+        self
+    }
+
+    /// Set the side length of rendered tiles in pixels.
+    pub fn set_tile_size(&mut self, tile_size: usize) -> &mut Self {
+        self.tile_size = tile_size.max(1);
+        self
+    }
+
+    /// Pixel bounds of the rendered region `(x_min, y_min, x_max, y_max)` (maxima exclusive).
+    pub fn region(&self) -> (usize, usize, usize, usize) {
+        match self.crop {
+            Some(crop) => crop.bounds(self.width, self.height),
+            None => (0, 0, self.width, self.height),
+        }
+    }
+
+    /// Tiles covering the rendered region as pixel bounds, row by row from the top left.
+    pub fn tiles(&self) -> Vec<(usize, usize, usize, usize)> {
+        let (x_min, y_min, x_max, y_max) = self.region();
+        let size = self.tile_size;
+        iproduct!((y_min..y_max).step_by(size), (x_min..x_max).step_by(size))
+            .map(|(y, x)| (x, y, (x + size).min(x_max), (y + size).min(y_max)))
+            .collect()
+    }
+
+    /// Render a world as seen by a camera.
+    pub fn render<RNG: RngCore>(&self, world: &World, camera: &dyn Camera, rng: &mut RNG) -> Image {
+        let (x_min, y_min, x_max, y_max) = self.region();
+        let (mut image, x_offset, y_offset) = match self.crop_output {
+            CropOutput::Cropped => (Image::new(x_max - x_min, y_max - y_min), x_min, y_min),
+            CropOutput::FullFrame => (Image::new(self.width, self.height), 0, 0),
+        };
+
+        let area = (x_max - x_min) * (y_max - y_min);
+        let mut rendered = 0;
+        let mut bar = progress::Bar::new();
+
+        for (tile_x_min, tile_y_min, tile_x_max, tile_y_max) in self.tiles() {
+            for (y, x) in iproduct!(tile_y_min..tile_y_max, tile_x_min..tile_x_max) {
+                // Image rows are stored from the top while pixel colors are calculated from the
+                // bottom.
+                let color =
+                    world.pixel_color(x, self.height - 1 - y, self.width, self.height, camera, rng);
+                image.pixels[(y - y_offset) * image.width + (x - x_offset)] = color;
+            }
+            rendered += (tile_x_max - tile_x_min) * (tile_y_max - tile_y_min);
+            bar.reach_percent((100.0 * rendered as f64 / area as f64) as i32);
+        }
+
+        bar.reach_percent(100);
+        bar.jobs_done();
+
+        image
+    }
+}
@@ -0,0 +1,218 @@
+//! Sampling distributions and multiple importance sampling helpers.
+
+/// Piecewise-constant one dimensional distribution over [0.0, 1.0).
+pub struct Distribution1D {
+    function: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    /// Construct a distribution proportional to non-negative function values over equal width
+    /// intervals.
+    ///
+    /// A function which is zero everywhere results in a uniform distribution.
+    pub fn new(function: Vec<f64>) -> Self {
+        let n = function.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + function[i] / n as f64;
+        }
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate().skip(1) {
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f64 / n as f64
+            };
+        }
+        Self {
+            function,
+            cdf,
+            integral,
+        }
+    }
+
+    /// Number of intervals.
+    pub fn count(&self) -> usize {
+        self.function.len()
+    }
+
+    /// Integral of the function over [0.0, 1.0).
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    /// Map a uniform random number to a sample `(x, pdf, interval)`.
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        // Last interval whose cumulative lower bound does not exceed `u`.
+        let offset = (self.cdf.partition_point(|&c| c <= u).max(1) - 1).min(self.count() - 1);
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = if width > 0.0 {
+            (u - self.cdf[offset]) / width
+        } else {
+            0.0
+        };
+        let x = (offset as f64 + du) / self.count() as f64;
+        (x, self.pdf_of_interval(offset), offset)
+    }
+
+    /// Probability density of samples within some interval.
+    fn pdf_of_interval(&self, offset: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.function[offset] / self.integral
+        } else {
+            1.0
+        }
+    }
+
+    /// Probability density of sampling `x`.
+    pub fn pdf(&self, x: f64) -> f64 {
+        let offset = ((x * self.count() as f64) as usize).min(self.count() - 1);
+        self.pdf_of_interval(offset)
+    }
+}
+
+/// Piecewise-constant two dimensional distribution over [0.0, 1.0)².
+///
+/// Samples the second (row) dimension from the marginal distribution, then the first (column)
+/// dimension from the chosen row's conditional distribution.
+pub struct Distribution2D {
+    conditionals: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// Construct a distribution proportional to row-major non-negative function values.
+    pub fn new(function: &[f64], width: usize, height: usize) -> Self {
+        let conditionals: Vec<Distribution1D> = function
+            .chunks(width)
+            .take(height)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(conditionals.iter().map(|c| c.integral()).collect());
+        Self {
+            conditionals,
+            marginal,
+        }
+    }
+
+    /// Integral of the function over [0.0, 1.0)².
+    pub fn integral(&self) -> f64 {
+        self.marginal.integral()
+    }
+
+    /// Map two uniform random numbers to a sample `((u, v), pdf)`.
+    pub fn sample(&self, u0: f64, u1: f64) -> ((f64, f64), f64) {
+        let (v, pdf_v, row) = self.marginal.sample(u1);
+        let (u, pdf_u, _) = self.conditionals[row].sample(u0);
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    /// Probability density of sampling `(u, v)`.
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let row = ((v * self.marginal.count() as f64) as usize).min(self.marginal.count() - 1);
+        self.marginal.pdf(v) * self.conditionals[row].pdf(u)
+    }
+}
+
+/// Power heuristic (β = 2) weight for a sample from one of two sampling strategies.
+///
+/// * `pdf` - Density of the strategy which produced the sample.
+/// * `other_pdf` - Density of the other strategy for the same sample.
+pub fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evenly spread uniform numbers in [0.0, 1.0).
+    fn uniforms(n: usize) -> impl Iterator<Item = f64> {
+        (0..n).map(move |i| (i as f64 + 0.5) / n as f64)
+    }
+
+    #[test]
+    fn distribution_1d_sample_matches_pdf() {
+        let distribution = Distribution1D::new(vec![1.0, 0.0, 3.0, 4.0]);
+        assert_eq!(distribution.integral(), 2.0);
+        for u in uniforms(1000) {
+            let (x, pdf, interval) = distribution.sample(u);
+            assert!((0.0..1.0).contains(&x));
+            assert_eq!(interval, (x * 4.0) as usize);
+            assert_ne!(interval, 1, "sampled an interval of zero weight");
+            assert_eq!(pdf, distribution.pdf(x));
+        }
+        assert_eq!(distribution.pdf(0.6), 1.5);
+    }
+
+    #[test]
+    fn distribution_1d_pdf_integrates_to_one() {
+        let distribution = Distribution1D::new(vec![0.5, 2.0, 0.25, 7.0, 1.0]);
+        let integral: f64 = (0..5)
+            .map(|i| distribution.pdf((i as f64 + 0.5) / 5.0) / 5.0)
+            .sum();
+        assert!((integral - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn distribution_1d_zero_function_is_uniform() {
+        let distribution = Distribution1D::new(vec![0.0; 4]);
+        assert_eq!(distribution.integral(), 0.0);
+        for u in uniforms(100) {
+            let (x, pdf, _) = distribution.sample(u);
+            assert!((x - u).abs() < 1e-12);
+            assert_eq!(pdf, 1.0);
+            assert_eq!(distribution.pdf(x), 1.0);
+        }
+    }
+
+    #[test]
+    fn distribution_2d_sample_matches_pdf() {
+        let function = [1.0, 2.0, 0.0, 0.5, 0.0, 0.0, 3.0, 1.0, 4.0];
+        let distribution = Distribution2D::new(&function, 3, 3);
+        for u0 in uniforms(30) {
+            for u1 in uniforms(30) {
+                let ((u, v), pdf) = distribution.sample(u0, u1);
+                assert!(pdf > 0.0);
+                assert!((pdf - distribution.pdf(u, v)).abs() < 1e-12);
+                let cell = (v * 3.0) as usize * 3 + (u * 3.0) as usize;
+                assert!(function[cell] > 0.0, "sampled a cell of zero weight");
+            }
+        }
+        let integral: f64 = (0..9)
+            .map(|i| {
+                let (u, v) = (
+                    (i % 3) as f64 / 3.0 + 1.0 / 6.0,
+                    (i / 3) as f64 / 3.0 + 1.0 / 6.0,
+                );
+                distribution.pdf(u, v) / 9.0
+            })
+            .sum();
+        assert!((integral - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn distribution_2d_zero_function_is_uniform() {
+        let distribution = Distribution2D::new(&[0.0; 6], 3, 2);
+        assert_eq!(distribution.integral(), 0.0);
+        let ((u, v), pdf) = distribution.sample(0.3, 0.8);
+        assert!((u - 0.3).abs() < 1e-12 && (v - 0.8).abs() < 1e-12);
+        assert_eq!(pdf, 1.0);
+        assert_eq!(distribution.pdf(u, v), 1.0);
+    }
+
+    #[test]
+    fn power_heuristic_weights_sum_to_one() {
+        let (a, b) = (0.3, 1.7);
+        assert!((power_heuristic(a, b) + power_heuristic(b, a) - 1.0).abs() < 1e-12);
+        assert_eq!(power_heuristic(1.0, 0.0), 1.0);
+        assert_eq!(power_heuristic(0.0, 0.0), 0.0);
+    }
+}
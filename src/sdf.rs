@@ -0,0 +1,428 @@
+//! Shapes defined by signed distance functions, rendered by sphere tracing.
+//!
+//! Distance functions and combinators follow
+//! [Inigo Quilez](https://iquilezles.org/articles/distfunctions/).
+use std::rc::Rc;
+
+use crate::aabb::Aabb;
+use crate::{Collidable, Collision, Material, Ray, Vec3};
+
+/// Signed distance function, negative inside a shape and positive outside.
+///
+/// Implemented for closures taking a point, so any `Fn(&Vec3) -> f64` can be traced.
+pub trait Sdf {
+    /// Signed distance from a point to the surface.
+    ///
+    /// Should never overestimate the true distance, which would let sphere tracing step through
+    /// the surface.
+    fn distance(&self, p: &Vec3) -> f64;
+}
+
+impl<F: Fn(&Vec3) -> f64> Sdf for F {
+    fn distance(&self, p: &Vec3) -> f64 {
+        self(p)
+    }
+}
+
+/// Collidable surface of a signed distance function, found by sphere tracing.
+///
+/// Surface coordinates are planar projections of the collision point along the dominant axis of
+/// its normal.
+pub struct SdfShape {
+    sdf: Box<dyn Sdf>,
+    bounds: Aabb,
+    material: Rc<dyn Material>,
+    max_steps: usize,
+    epsilon: f64,
+    step_scale: f64,
+}
+
+impl SdfShape {
+    /// Construct a distance function shape.
+    ///
+    /// * `sdf` - Distance function defining the shape.
+    /// * `bounds` - Box outside of which the surface is not traced.
+    pub fn new(sdf: Box<dyn Sdf>, bounds: Aabb, material: Rc<dyn Material>) -> Self {
+        Self {
+            sdf,
+            bounds,
+            material,
+            max_steps: 256,
+            epsilon: 1e-4,
+            step_scale: 1.0,
+        }
+    }
+
+    /// Set the maximum number of tracing steps before a ray is considered to miss.
+    pub fn set_max_steps(&mut self, max_steps: usize) -> &mut Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Set the distance from the surface within which a ray is considered to collide.
+    pub fn set_epsilon(&mut self, epsilon: f64) -> &mut Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Set the fraction of the distance advanced each step.
+    ///
+    /// Values below one are needed for distance functions which overestimate (e.g. `Twist`).
+    pub fn set_step_scale(&mut self, step_scale: f64) -> &mut Self {
+        self.step_scale = step_scale;
+        self
+    }
+
+    /// Outward unit normal at a point from the gradient of the distance function.
+    ///
+    /// Uses the tetrahedral central difference, needing four evaluations.
+    fn normal(&self, p: &Vec3) -> Vec3 {
+        let h = self.epsilon;
+        [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ]
+        .iter()
+        .fold(Vec3::zero(), |sum, k| {
+            sum + k * self.sdf.distance(&(p + k * h))
+        })
+        .normalized()
+    }
+}
+
+impl Collidable for SdfShape {
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        let (t_start, t_end) = self.bounds.clip(ray, t_min, t_max)?;
+        let length = ray.direction.magnitude();
+        let mut t = t_start;
+        // Step off the surface when starting on it (e.g. for rays scattered from it).
+        let mut start = self.sdf.distance(&ray.at(t));
+        while start.abs() < self.epsilon && t < t_end {
+            t += 2.0 * self.epsilon / length;
+            start = self.sdf.distance(&ray.at(t));
+        }
+        // Trace towards the surface from whichever side the ray starts on.
+        let side = start.signum();
+        for _ in 0..self.max_steps {
+            let p = ray.at(t);
+            let distance = side * self.sdf.distance(&p);
+            if distance < self.epsilon {
+                let normal = self.normal(&p);
+                let uv = if normal.x.abs() > normal.y.abs() && normal.x.abs() > normal.z.abs() {
+                    (p.z, p.y)
+                } else if normal.y.abs() > normal.z.abs() {
+                    (p.x, p.z)
+                } else {
+                    (p.x, p.y)
+                };
+                return Some(Collision::new(
+                    ray,
+                    t,
+                    p,
+                    normal,
+                    uv,
+                    Vec3::zero(),
+                    self.material.clone(),
+                ));
+            }
+            t += distance * self.step_scale / length;
+            if t >= t_end {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
+
+/// Sphere centered at the origin.
+pub struct Ball {
+    radius: f64,
+}
+
+impl Ball {
+    pub fn new(radius: f64) -> Self {
+        Self { radius }
+    }
+}
+
+impl Sdf for Ball {
+    fn distance(&self, p: &Vec3) -> f64 {
+        p.magnitude() - self.radius
+    }
+}
+
+/// Box centered at the origin with edges rounded by a radius (sharp if zero).
+pub struct RoundedBox {
+    half_extents: Vec3,
+    radius: f64,
+}
+
+impl RoundedBox {
+    /// Construct a rounded box.
+    ///
+    /// * `half_extents` - Distance from the center to the faces along each axis.
+    /// * `radius` - Radius of the rounded edges, within the extents.
+    pub fn new(half_extents: Vec3, radius: f64) -> Self {
+        Self {
+            half_extents,
+            radius,
+        }
+    }
+}
+
+impl Sdf for RoundedBox {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let q = Vec3::new(p.x.abs(), p.y.abs(), p.z.abs()) - self.half_extents + self.radius;
+        let outside = q.max(&Vec3::zero()).magnitude();
+        let inside = q.x.max(q.y).max(q.z).min(0.0);
+        outside + inside - self.radius
+    }
+}
+
+/// Torus centered at the origin about the Y axis.
+pub struct Ring {
+    major_radius: f64,
+    minor_radius: f64,
+}
+
+impl Ring {
+    /// Construct a ring.
+    ///
+    /// * `major_radius` - Distance from the center to the middle of the tube.
+    /// * `minor_radius` - Radius of the tube.
+    pub fn new(major_radius: f64, minor_radius: f64) -> Self {
+        Self {
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl Sdf for Ring {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let x = (p.x * p.x + p.z * p.z).sqrt() - self.major_radius;
+        (x * x + p.y * p.y).sqrt() - self.minor_radius
+    }
+}
+
+/// Line segment thickened by a radius (a capsule).
+pub struct Segment {
+    a: Vec3,
+    b: Vec3,
+    radius: f64,
+}
+
+impl Segment {
+    pub fn new(a: Vec3, b: Vec3, radius: f64) -> Self {
+        Self { a, b, radius }
+    }
+}
+
+impl Sdf for Segment {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let pa = p - self.a;
+        let ba = self.b - self.a;
+        let h = (pa.dot(&ba) / ba.magnitude_squared()).clamp(0.0, 1.0);
+        (pa - ba * h).magnitude() - self.radius
+    }
+}
+
+/// Menger sponge fractal filling the cube from -1.0 to 1.0.
+pub struct MengerSponge {
+    iterations: usize,
+}
+
+impl MengerSponge {
+    pub fn new(iterations: usize) -> Self {
+        Self { iterations }
+    }
+}
+
+impl Sdf for MengerSponge {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let mut distance = RoundedBox::new(Vec3::one(), 0.0).distance(p);
+        let mut scale = 1.0;
+        for _ in 0..self.iterations {
+            let a = Vec3::new(
+                (p.x * scale).rem_euclid(2.0) - 1.0,
+                (p.y * scale).rem_euclid(2.0) - 1.0,
+                (p.z * scale).rem_euclid(2.0) - 1.0,
+            );
+            scale *= 3.0;
+            let r = Vec3::new(
+                (1.0 - 3.0 * a.x.abs()).abs(),
+                (1.0 - 3.0 * a.y.abs()).abs(),
+                (1.0 - 3.0 * a.z.abs()).abs(),
+            );
+            let cross = r.x.max(r.y).min(r.y.max(r.z)).min(r.z.max(r.x));
+            distance = distance.max((cross - 1.0) / scale);
+        }
+        distance
+    }
+}
+
+/// Shape moved by an offset.
+pub struct Translate {
+    sdf: Box<dyn Sdf>,
+    offset: Vec3,
+}
+
+impl Translate {
+    pub fn new(sdf: Box<dyn Sdf>, offset: Vec3) -> Self {
+        Self { sdf, offset }
+    }
+}
+
+impl Sdf for Translate {
+    fn distance(&self, p: &Vec3) -> f64 {
+        self.sdf.distance(&(p - self.offset))
+    }
+}
+
+/// Union of two shapes.
+pub struct Union {
+    a: Box<dyn Sdf>,
+    b: Box<dyn Sdf>,
+}
+
+impl Union {
+    pub fn new(a: Box<dyn Sdf>, b: Box<dyn Sdf>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Sdf for Union {
+    fn distance(&self, p: &Vec3) -> f64 {
+        self.a.distance(p).min(self.b.distance(p))
+    }
+}
+
+/// Union of two shapes blended together where they meet.
+pub struct SmoothUnion {
+    a: Box<dyn Sdf>,
+    b: Box<dyn Sdf>,
+    smoothness: f64,
+}
+
+impl SmoothUnion {
+    /// Construct a smooth union.
+    ///
+    /// * `smoothness` - Distance over which the shapes are blended.
+    pub fn new(a: Box<dyn Sdf>, b: Box<dyn Sdf>, smoothness: f64) -> Self {
+        Self { a, b, smoothness }
+    }
+}
+
+impl Sdf for SmoothUnion {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let a = self.a.distance(p);
+        let b = self.b.distance(p);
+        let k = self.smoothness;
+        let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+        b * (1.0 - h) + a * h - k * h * (1.0 - h)
+    }
+}
+
+/// Intersection of two shapes.
+pub struct Intersect {
+    a: Box<dyn Sdf>,
+    b: Box<dyn Sdf>,
+}
+
+impl Intersect {
+    pub fn new(a: Box<dyn Sdf>, b: Box<dyn Sdf>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Sdf for Intersect {
+    fn distance(&self, p: &Vec3) -> f64 {
+        self.a.distance(p).max(self.b.distance(p))
+    }
+}
+
+/// First shape with the second carved out of it.
+pub struct Subtract {
+    a: Box<dyn Sdf>,
+    b: Box<dyn Sdf>,
+}
+
+impl Subtract {
+    pub fn new(a: Box<dyn Sdf>, b: Box<dyn Sdf>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Sdf for Subtract {
+    fn distance(&self, p: &Vec3) -> f64 {
+        self.a.distance(p).max(-self.b.distance(p))
+    }
+}
+
+/// Shape repeated infinitely on a grid.
+pub struct Repeat {
+    sdf: Box<dyn Sdf>,
+    period: Vec3,
+}
+
+impl Repeat {
+    /// Construct a repetition.
+    ///
+    /// * `period` - Grid spacing along each axis (zero for no repetition along that axis).
+    pub fn new(sdf: Box<dyn Sdf>, period: Vec3) -> Self {
+        Self { sdf, period }
+    }
+}
+
+impl Sdf for Repeat {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let wrap = |x: f64, period: f64| {
+            if period > 0.0 {
+                x - period * (x / period).round()
+            } else {
+                x
+            }
+        };
+        let q = Vec3::new(
+            wrap(p.x, self.period.x),
+            wrap(p.y, self.period.y),
+            wrap(p.z, self.period.z),
+        );
+        self.sdf.distance(&q)
+    }
+}
+
+/// Shape twisted about the Y axis.
+///
+/// Distances are overestimated for strong twists, so shapes should be traced with a reduced step
+/// scale.
+pub struct Twist {
+    sdf: Box<dyn Sdf>,
+    rate: f64,
+}
+
+impl Twist {
+    /// Construct a twist.
+    ///
+    /// * `rate` - Rotation in degrees per unit distance along the Y axis.
+    pub fn new(sdf: Box<dyn Sdf>, rate: f64) -> Self {
+        Self {
+            sdf,
+            rate: rate.to_radians(),
+        }
+    }
+}
+
+impl Sdf for Twist {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let (sin, cos) = (self.rate * p.y).sin_cos();
+        let q = Vec3::new(cos * p.x - sin * p.z, p.y, sin * p.x + cos * p.z);
+        self.sdf.distance(&q)
+    }
+}
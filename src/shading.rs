@@ -0,0 +1,167 @@
+//! Material wrappers which perturb shading normals to add small-scale surface detail.
+use std::rc::Rc;
+
+use crate::{Collision, Material, Ray, Texture, Vec3};
+
+/// Offset in surface coordinates over which height map derivatives are estimated.
+const BUMP_DELTA: f64 = 1.0 / 1024.0;
+
+/// Copy of a collision with the shading normal replaced by a normal in tangent space.
+///
+/// Tangent space has X along the collision tangent, Y along increasing `v` and Z along the outward
+/// surface normal. The geometric normal is left unchanged.
+fn perturb(ray_in: &Ray, collision: &Collision, local: &Vec3) -> Collision {
+    let outward = if collision.front_face {
+        collision.normal
+    } else {
+        -collision.normal
+    };
+    let tangent = (collision.tangent - outward * collision.tangent.dot(&outward)).normalized();
+    let bitangent = outward.cross(&tangent);
+    let normal = (tangent * local.x + bitangent * local.y + outward * local.z).normalized();
+    let normal = if collision.front_face { normal } else { -normal };
+
+    // Keep the unperturbed normal where the perturbed one faces away from the viewer.
+    let mut perturbed = collision.clone();
+    if normal.dot(&ray_in.direction) < 0.0 {
+        perturbed.normal = normal;
+    }
+    perturbed
+}
+
+/// Material with shading normals read from a tangent-space normal map.
+///
+/// Map values in [0.0, 1.0] are remapped to [-1.0, 1.0], so the map should be loaded without
+/// color space conversion (e.g. with `ImageTexture::open_linear`).
+pub struct NormalMap {
+    material: Rc<dyn Material>,
+    map: Rc<dyn Texture>,
+    strength: f64,
+}
+
+impl NormalMap {
+    /// Construct a normal mapped material.
+    ///
+    /// * `material` - Material shaded with the perturbed normals.
+    /// * `map` - Tangent-space normal map.
+    pub fn new(material: Rc<dyn Material>, map: Rc<dyn Texture>) -> Self {
+        Self {
+            material,
+            map,
+            strength: 1.0,
+        }
+    }
+
+    /// Set the factor by which the tangential components of mapped normals are scaled.
+    pub fn set_strength(&mut self, strength: f64) -> &mut Self {
+        self.strength = strength;
+        self
+    }
+
+    /// Collision with the mapped shading normal.
+    fn shade(&self, ray_in: &Ray, collision: &Collision) -> Collision {
+        let value = self.map.value(collision) * 2.0 - Vec3::one();
+        let local = Vec3::new(
+            value.x * self.strength,
+            value.y * self.strength,
+            value.z.max(1e-3),
+        );
+        perturb(ray_in, collision, &local)
+    }
+}
+
+impl Material for NormalMap {
+    fn scatter(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3)> {
+        self.material.scatter(ray_in, &self.shade(ray_in, collision))
+    }
+
+    fn scatter_lobe(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3, bool)> {
+        self.material
+            .scatter_lobe(ray_in, &self.shade(ray_in, collision))
+    }
+
+    fn evaluate(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> Vec3 {
+        self.material
+            .evaluate(ray_in, &self.shade(ray_in, collision), direction)
+    }
+
+    fn pdf(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> f64 {
+        self.material
+            .pdf(ray_in, &self.shade(ray_in, collision), direction)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.material.is_specular()
+    }
+
+    fn emitted(&self, ray_in: &Ray, collision: &Collision) -> Vec3 {
+        self.material.emitted(ray_in, collision)
+    }
+}
+
+/// Material with shading normals derived from the slope of a grayscale height map.
+pub struct BumpMap {
+    material: Rc<dyn Material>,
+    height: Rc<dyn Texture>,
+    scale: f64,
+}
+
+impl BumpMap {
+    /// Construct a bump mapped material.
+    ///
+    /// * `material` - Material shaded with the perturbed normals.
+    /// * `height` - Height map, read from the first (X) component.
+    /// * `scale` - Height displacement per unit of surface coordinates.
+    pub fn new(material: Rc<dyn Material>, height: Rc<dyn Texture>, scale: f64) -> Self {
+        Self {
+            material,
+            height,
+            scale,
+        }
+    }
+
+    /// Collision with the bumped shading normal.
+    fn shade(&self, ray_in: &Ray, collision: &Collision) -> Collision {
+        let mut offset = collision.clone();
+        let height = self.height.value(collision).x;
+        offset.u = collision.u + BUMP_DELTA;
+        let du = (self.height.value(&offset).x - height) / BUMP_DELTA;
+        offset.u = collision.u;
+        offset.v = collision.v + BUMP_DELTA;
+        let dv = (self.height.value(&offset).x - height) / BUMP_DELTA;
+        perturb(
+            ray_in,
+            collision,
+            &Vec3::new(-du * self.scale, -dv * self.scale, 1.0),
+        )
+    }
+}
+
+impl Material for BumpMap {
+    fn scatter(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3)> {
+        self.material.scatter(ray_in, &self.shade(ray_in, collision))
+    }
+
+    fn scatter_lobe(&self, ray_in: &Ray, collision: &Collision) -> Option<(Ray, Vec3, bool)> {
+        self.material
+            .scatter_lobe(ray_in, &self.shade(ray_in, collision))
+    }
+
+    fn evaluate(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> Vec3 {
+        self.material
+            .evaluate(ray_in, &self.shade(ray_in, collision), direction)
+    }
+
+    fn pdf(&self, ray_in: &Ray, collision: &Collision, direction: &Vec3) -> f64 {
+        self.material
+            .pdf(ray_in, &self.shade(ray_in, collision), direction)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.material.is_specular()
+    }
+
+    fn emitted(&self, ray_in: &Ray, collision: &Collision) -> Vec3 {
+        self.material.emitted(ray_in, collision)
+    }
+}
@@ -0,0 +1,245 @@
+//! Analytic daylight sky model.
+//!
+//! Based on "A Practical Analytic Model for Daylight" (Preetham, Shirley, Smits 1999).
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::environment::Environment;
+use crate::Vec3;
+
+/// Angular radius of the sun disk in degrees.
+const SUN_ANGULAR_RADIUS: f64 = 0.2665;
+
+/// Luminance of the sun disk outside the atmosphere in kcd/m².
+const SUN_LUMINANCE: f64 = 1.6e6;
+
+/// Wavelengths in micrometers representative of the red, green and blue channels.
+const WAVELENGTHS: [f64; 3] = [0.680, 0.550, 0.440];
+
+/// Coefficients `(A, B, C, D, E)` of the Perez sky luminance distribution.
+type Perez = [f64; 5];
+
+/// Perez sky luminance distribution relative to the zenith.
+fn perez(coefficients: &Perez, cos_theta: f64, gamma: f64) -> f64 {
+    let [a, b, c, d, e] = *coefficients;
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+/// Convert a CIE xyY color to linear sRGB.
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Vec3 {
+    let cx = x / y * luminance;
+    let cz = (1.0 - x - y) / y * luminance;
+    Vec3::new(
+        3.2406 * cx - 1.5372 * luminance - 0.4986 * cz,
+        -0.9689 * cx + 1.8758 * luminance + 0.0415 * cz,
+        0.0557 * cx - 0.2040 * luminance + 1.0570 * cz,
+    )
+}
+
+/// Preetham daylight sky with a sun disk.
+///
+/// Directions below the horizon see the horizon sky color scaled by a ground color.
+pub struct PreethamSky {
+    // User set state:
+    elevation: f64,
+    azimuth: f64,
+    turbidity: f64,
+    intensity: f64,
+    ground: Vec3,
+    // Calculated state:
+    sun_direction: Vec3,
+    sun_radiance: Vec3,
+    zenith: Vec3,
+    coefficients: [Perez; 3],
+}
+
+impl PreethamSky {
+    /// Construct a sky with the sun overhead.
+    pub fn new() -> Self {
+        let mut sky = Self {
+            elevation: 90.0,
+            azimuth: 0.0,
+            turbidity: 2.5,
+            intensity: 0.05,
+            ground: Vec3::zero(),
+            sun_direction: Vec3::zero(),
+            sun_radiance: Vec3::zero(),
+            zenith: Vec3::zero(),
+            coefficients: [[0.0; 5]; 3],
+        };
+        sky.update();
+        sky
+    }
+
+    /// Update computed sky state.
+    pub fn update(&mut self) -> &mut Self {
+        let elevation = self.elevation.to_radians();
+        let azimuth = self.azimuth.to_radians();
+        self.sun_direction = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+
+        let t = self.turbidity;
+        let theta_s = PI / 2.0 - elevation.max(0.0);
+        let (t1, t2, t3) = (theta_s, theta_s * theta_s, theta_s * theta_s * theta_s);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_x = t * t * (0.00166 * t3 - 0.00375 * t2 + 0.00209 * t1)
+            + t * (-0.02903 * t3 + 0.06377 * t2 - 0.03202 * t1 + 0.00394)
+            + (0.11693 * t3 - 0.21196 * t2 + 0.06052 * t1 + 0.25886);
+        let zenith_y = t * t * (0.00275 * t3 - 0.00610 * t2 + 0.00317 * t1)
+            + t * (-0.04214 * t3 + 0.08970 * t2 - 0.04153 * t1 + 0.00516)
+            + (0.15346 * t3 - 0.26756 * t2 + 0.06670 * t1 + 0.26688);
+
+        let coefficients = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+        // Normalize by the distribution at the zenith so the zenith values are reproduced.
+        let cos_theta_s = theta_s.cos();
+        self.zenith = Vec3::new(
+            zenith_luminance / perez(&coefficients[0], 1.0, theta_s),
+            zenith_x / perez(&coefficients[1], 1.0, theta_s),
+            zenith_y / perez(&coefficients[2], 1.0, theta_s),
+        );
+        self.coefficients = coefficients;
+
+        // Sun disk attenuated by Rayleigh and aerosol (Ångström) extinction along the optical air
+        // mass towards the sun.
+        let air_mass = 1.0 / (cos_theta_s + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+        let beta = 0.04608 * t - 0.04586;
+        let transmittance: Vec<f64> = WAVELENGTHS
+            .iter()
+            .map(|&lambda| {
+                let rayleigh = (-0.008735 * lambda.powf(-4.08) * air_mass).exp();
+                let aerosol = (-beta * lambda.powf(-1.3) * air_mass).exp();
+                rayleigh * aerosol
+            })
+            .collect();
+        self.sun_radiance = if self.elevation > -SUN_ANGULAR_RADIUS {
+            Vec3::new(transmittance[0], transmittance[1], transmittance[2]) * SUN_LUMINANCE
+        } else {
+            Vec3::zero()
+        };
+        self
+    }
+
+    /// Set the angle of the sun above the horizon in degrees.
+    pub fn set_elevation(&mut self, elevation: f64) -> &mut Self {
+        self.elevation = elevation;
+        self
+    }
+
+    /// Set the angle of the sun clockwise from -Z towards +X in degrees.
+    pub fn set_azimuth(&mut self, azimuth: f64) -> &mut Self {
+        self.azimuth = azimuth;
+        self
+    }
+
+    /// Set the atmospheric turbidity (2.0 for very clear to 10.0 for hazy skies).
+    pub fn set_turbidity(&mut self, turbidity: f64) -> &mut Self {
+        self.turbidity = turbidity;
+        self
+    }
+
+    /// Set the multiplier converting luminance in kcd/m² to scene radiance.
+    pub fn set_intensity(&mut self, intensity: f64) -> &mut Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Set the color by which the horizon is scaled for directions below it.
+    pub fn set_ground(&mut self, ground: Vec3) -> &mut Self {
+        self.ground = ground;
+        self
+    }
+
+    /// Cosine of the sun disk angular radius.
+    fn cos_sun_radius() -> f64 {
+        SUN_ANGULAR_RADIUS.to_radians().cos()
+    }
+
+    /// Sky color excluding the sun disk.
+    fn sky_color(&self, direction: &Vec3) -> Vec3 {
+        let cos_theta = direction.y.max(0.001);
+        let above_horizon = Vec3::new(direction.x, cos_theta, direction.z).normalized();
+        let gamma = above_horizon.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+        let luminance = self.zenith.x * perez(&self.coefficients[0], cos_theta, gamma);
+        let x = self.zenith.y * perez(&self.coefficients[1], cos_theta, gamma);
+        let y = self.zenith.z * perez(&self.coefficients[2], cos_theta, gamma);
+        let rgb = xyy_to_rgb(x, y, luminance.max(0.0));
+        let rgb = Vec3::new(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0));
+        if direction.y < 0.0 {
+            rgb.hadamard_product(&self.ground)
+        } else {
+            rgb
+        }
+    }
+}
+
+impl Default for PreethamSky {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment for PreethamSky {
+    fn color(&self, direction: &Vec3) -> Vec3 {
+        let d = direction.normalized();
+        let mut color = self.sky_color(&d);
+        if d.y >= 0.0 && d.dot(&self.sun_direction) >= Self::cos_sun_radius() {
+            color += self.sun_radiance;
+        }
+        color * self.intensity
+    }
+
+    /// Sample a direction uniformly within the sun disk.
+    fn sample(&self) -> Option<(Vec3, f64)> {
+        if self.sun_radiance.magnitude_squared() <= 0.0 {
+            return None;
+        }
+        let mut rng = rand::thread_rng();
+        let cos_max = Self::cos_sun_radius();
+        let cos_theta = 1.0 - rng.gen::<f64>() * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let (s, t) = self.sun_direction.orthonormal_basis();
+        let direction =
+            (s * phi.cos() + t * phi.sin()) * sin_theta + self.sun_direction * cos_theta;
+        Some((direction, 1.0 / (2.0 * PI * (1.0 - cos_max))))
+    }
+
+    fn pdf(&self, direction: &Vec3) -> f64 {
+        let cos_max = Self::cos_sun_radius();
+        if self.sun_radiance.magnitude_squared() > 0.0
+            && direction.normalized().dot(&self.sun_direction) >= cos_max
+        {
+            1.0 / (2.0 * PI * (1.0 - cos_max))
+        } else {
+            0.0
+        }
+    }
+}
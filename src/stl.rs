@@ -0,0 +1,172 @@
+//! Stereolithography (STL) mesh loader.
+//!
+//! Reads ASCII and binary files. STL stores each triangle with its own vertices and a facet
+//! normal, so meshes are flat shaded with unshared vertices.
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::mesh::Mesh;
+use crate::{Material, Vec3};
+
+/// Construct an invalid data error.
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("STL: {}", message))
+}
+
+/// Load a mesh from an STL file.
+pub fn open<P: AsRef<Path>>(path: P, material: Rc<dyn Material>) -> Result<Mesh> {
+    parse(&fs::read(path)?, material)
+}
+
+/// Parse a mesh from the contents of an STL file.
+///
+/// Files are binary if their size matches the triangle count in the binary header, as binary
+/// headers may also begin with `solid`.
+pub fn parse(bytes: &[u8], material: Rc<dyn Material>) -> Result<Mesh> {
+    let binary = bytes.len() >= 84 && {
+        let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+        bytes.len() == 84 + 50 * count
+    };
+    let positions = if binary {
+        parse_binary(&bytes[84..])
+    } else {
+        parse_ascii(bytes)?
+    };
+    let indices = (0..positions.len() / 3)
+        .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+        .collect();
+    Ok(Mesh::new(positions, indices, material))
+}
+
+/// Vertex positions of each binary triangle record, ignoring facet normals and attributes.
+fn parse_binary(records: &[u8]) -> Vec<Vec3> {
+    let float = |b: &[u8]| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64;
+    records
+        .chunks_exact(50)
+        .flat_map(|record| {
+            (0..3).map(move |v| {
+                let b = &record[12 + 12 * v..];
+                Vec3::new(float(b), float(&b[4..]), float(&b[8..]))
+            })
+        })
+        .collect()
+}
+
+/// Vertex positions of each ASCII `vertex` line, grouped in threes by facet.
+fn parse_ascii(bytes: &[u8]) -> Result<Vec<Vec3>> {
+    let text = std::str::from_utf8(bytes).map_err(|_| invalid("invalid ASCII file"))?;
+    if !text.trim_start().starts_with("solid") {
+        return Err(invalid("missing `solid`"));
+    }
+    let mut positions = Vec::new();
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("vertex") {
+            continue;
+        }
+        let mut coordinate = || -> Result<f64> {
+            let word = words.next().ok_or_else(|| invalid("incomplete vertex"))?;
+            word.parse()
+                .map_err(|_| invalid(&format!("invalid coordinate `{}`", word)))
+        };
+        positions.push(Vec3::new(coordinate()?, coordinate()?, coordinate()?));
+    }
+    if positions.len() % 3 != 0 {
+        return Err(invalid("facet without three vertices"));
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambert;
+
+    fn material() -> Rc<dyn Material> {
+        Rc::new(Lambert::new(Vec3::new(0.5, 0.5, 0.5)))
+    }
+
+    /// Binary file with some triangles, each `[0, 0, 0], [1, 0, 0], [0, 1, 0]` offset along z by
+    /// its index, and a header beginning with `solid`.
+    fn binary(count: u32) -> Vec<u8> {
+        let mut bytes = b"solid binary header".to_vec();
+        bytes.resize(80, 0);
+        bytes.extend_from_slice(&count.to_le_bytes());
+        for i in 0..count {
+            let z = i as f32;
+            let floats = [
+                0.0, 0.0, 1.0, // Facet normal.
+                0.0, 0.0, z, 1.0, 0.0, z, 0.0, 1.0, z,
+            ];
+            for value in &floats {
+                bytes.extend_from_slice(&f32::to_le_bytes(*value));
+            }
+            bytes.extend_from_slice(&[0, 0]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn ascii() {
+        let source = b"solid triangle
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 1
+      vertex 1 0 1
+      vertex 0 1 1.5e0
+    endloop
+  endfacet
+endsolid triangle
+";
+        let mesh = parse(source, material()).unwrap();
+        assert_eq!(mesh.positions().len(), 6);
+        assert_eq!(mesh.positions()[5].z, 1.5);
+        assert_eq!(mesh.indices(), &[[0, 1, 2], [3, 4, 5]]);
+    }
+
+    #[test]
+    fn binary_with_solid_header() {
+        let mesh = parse(&binary(2), material()).unwrap();
+        assert_eq!(mesh.positions().len(), 6);
+        assert_eq!(mesh.positions()[4].x, 1.0);
+        assert_eq!(mesh.positions()[4].z, 1.0);
+        assert_eq!(mesh.indices(), &[[0, 1, 2], [3, 4, 5]]);
+    }
+
+    #[test]
+    fn binary_requires_matching_size() {
+        // With a size other than `84 + 50 * count` the file is read as ASCII, which a binary
+        // header beginning with `solid` passes but its body does not.
+        let mut bytes = binary(2);
+        bytes.push(0);
+        assert!(parse(&bytes, material()).is_err());
+
+        let mut bytes = binary(2);
+        bytes.truncate(84 + 50);
+        assert!(parse(&bytes, material()).is_err());
+    }
+
+    #[test]
+    fn incomplete_ascii_facet_is_an_error() {
+        let source = b"solid triangle
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+    endloop
+  endfacet
+endsolid triangle
+";
+        let error = parse(source, material()).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
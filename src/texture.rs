@@ -0,0 +1,143 @@
+//! Textures which vary material parameters over surfaces.
+use std::path::Path;
+use std::rc::Rc;
+
+use image::ImageResult;
+
+use crate::{Collision, Vec3};
+
+/// A value which varies over the surfaces of collidables.
+///
+/// Scalar parameters read the first (X) component.
+pub trait Texture {
+    /// Texture value at a collision.
+    fn value(&self, collision: &Collision) -> Vec3;
+}
+
+/// Constant color texture.
+impl Texture for Vec3 {
+    fn value(&self, _collision: &Collision) -> Vec3 {
+        *self
+    }
+}
+
+/// Constant scalar texture.
+impl Texture for f64 {
+    fn value(&self, _collision: &Collision) -> Vec3 {
+        Vec3::new(*self, *self, *self)
+    }
+}
+
+/// Convert a color from the sRGB transfer function to linear values.
+pub fn srgb_to_linear(color: &Vec3) -> Vec3 {
+    let decode = |c: f64| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    Vec3::new(decode(color.x), decode(color.y), decode(color.z))
+}
+
+/// Image texture looked up by surface coordinates, with `v` increasing from the bottom row.
+///
+/// Coordinates outside of [0.0, 1.0] wrap around.
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl ImageTexture {
+    /// Construct a texture from row-major linear pixels (top row first).
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> Self {
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Load a color texture from an image file, converting from sRGB to linear values.
+    pub fn open<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        let texture = Self::open_linear(path)?;
+        let pixels = texture.pixels.iter().map(srgb_to_linear).collect();
+        Ok(Self::new(texture.width, texture.height, pixels))
+    }
+
+    /// Load a data texture (e.g. roughness or normals) from an image file without conversion.
+    pub fn open_linear<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        let image = image::open(path)?.into_rgb32f();
+        let (width, height) = image.dimensions();
+        let pixels = image
+            .pixels()
+            .map(|p| Vec3::new(p.0[0] as f64, p.0[1] as f64, p.0[2] as f64))
+            .collect();
+        Ok(Self::new(width as usize, height as usize, pixels))
+    }
+
+    /// Pixel value at surface coordinates.
+    pub fn lookup(&self, u: f64, v: f64) -> Vec3 {
+        let u = u.rem_euclid(1.0);
+        let v = 1.0 - v.rem_euclid(1.0);
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.pixels[j * self.width + i]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, collision: &Collision) -> Vec3 {
+        self.lookup(collision.u, collision.v)
+    }
+}
+
+/// Color interpolated from the vertices of collided meshes, or white for other surfaces.
+pub struct VertexColor;
+
+impl Texture for VertexColor {
+    fn value(&self, collision: &Collision) -> Vec3 {
+        collision.color.unwrap_or_else(Vec3::one)
+    }
+}
+
+/// Element-wise product of two textures, e.g. to scale a texture by a constant factor.
+pub struct ProductTexture {
+    a: Rc<dyn Texture>,
+    b: Rc<dyn Texture>,
+}
+
+impl ProductTexture {
+    pub fn new(a: Rc<dyn Texture>, b: Rc<dyn Texture>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Texture for ProductTexture {
+    fn value(&self, collision: &Collision) -> Vec3 {
+        self.a.value(collision).hadamard_product(&self.b.value(collision))
+    }
+}
+
+/// Single channel of a texture as a scalar texture, e.g. for maps packing several parameters.
+pub struct ChannelTexture {
+    texture: Rc<dyn Texture>,
+    channel: usize,
+}
+
+impl ChannelTexture {
+    /// Construct a channel texture.
+    ///
+    /// * `channel` - Index of the channel (0, 1, 2 for red, green, blue).
+    pub fn new(texture: Rc<dyn Texture>, channel: usize) -> Self {
+        Self { texture, channel }
+    }
+}
+
+impl Texture for ChannelTexture {
+    fn value(&self, collision: &Collision) -> Vec3 {
+        let value = self.texture.value(collision)[self.channel];
+        Vec3::new(value, value, value)
+    }
+}
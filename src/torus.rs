@@ -0,0 +1,125 @@
+//! Collidable torus.
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use crate::aabb::Aabb;
+use crate::collide::Interval;
+use crate::microfacet::Frame;
+use crate::polynomial::solve_quartic;
+use crate::{Collidable, Collision, Material, Ray, Vec3};
+
+/// Collidable torus (ring) about an axis.
+///
+/// Surface coordinates wrap `u` around the axis and `v` around the tube, starting from the outer
+/// equator.
+pub struct Torus {
+    center: Vec3,
+    frame: Frame,
+    major_radius: f64,
+    minor_radius: f64,
+    material: Rc<dyn Material>,
+}
+
+impl Torus {
+    /// Construct a torus.
+    ///
+    /// * `center` - Center of the ring.
+    /// * `axis` - Direction of the axis about which the ring is swept.
+    /// * `major_radius` - Distance from the center to the middle of the tube.
+    /// * `minor_radius` - Radius of the tube.
+    pub fn new(
+        center: Vec3,
+        axis: Vec3,
+        major_radius: f64,
+        minor_radius: f64,
+        material: Rc<dyn Material>,
+    ) -> Self {
+        Self {
+            center,
+            frame: Frame::new(axis.normalized()),
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+
+    /// Distances along a ray at which it crosses the surface, in increasing order.
+    fn roots(&self, ray: &Ray) -> Vec<f64> {
+        let length = ray.direction.magnitude();
+        let d = self.frame.to_local(&ray.direction) / length;
+        let o = self.frame.to_local(&(ray.point - self.center));
+        // Start from the point closest to the center, keeping coefficients well conditioned for
+        // distant rays.
+        let t_closest = -o.dot(&d);
+        let o = o + d * t_closest;
+
+        let r2 = self.major_radius * self.major_radius;
+        let n = o.dot(&d);
+        let k = o.magnitude_squared() + r2 - self.minor_radius * self.minor_radius;
+        let roots = solve_quartic(
+            1.0,
+            4.0 * n,
+            4.0 * n * n + 2.0 * k - 4.0 * r2 * (d.x * d.x + d.y * d.y),
+            4.0 * n * k - 8.0 * r2 * (o.x * d.x + o.y * d.y),
+            k * k - 4.0 * r2 * (o.x * o.x + o.y * o.y),
+        );
+        roots
+            .into_iter()
+            .map(|t| (t + t_closest) / length)
+            .collect()
+    }
+
+    /// Collision record at a distance along a ray which crosses the surface.
+    fn collision_at(&self, ray: &Ray, t: f64) -> Collision {
+        let p = ray.at(t);
+        let local = self.frame.to_local(&(p - self.center));
+
+        // Outward normal points away from the nearest point on the ring through the tube.
+        let radial = Vec3::new(local.x, local.y, 0.0);
+        let ring = if radial.magnitude_squared() > 0.0 {
+            radial.normalized() * self.major_radius
+        } else {
+            Vec3::zero()
+        };
+        let normal = (local - ring).normalized();
+
+        let phi = local.y.atan2(local.x);
+        let theta = local.z.atan2(radial.magnitude() - self.major_radius);
+        let u = phi.rem_euclid(2.0 * PI) / (2.0 * PI);
+        let v = theta.rem_euclid(2.0 * PI) / (2.0 * PI);
+
+        Collision::new(
+            ray,
+            t,
+            p,
+            self.frame.to_world(&normal),
+            (u, v),
+            self.frame.to_world(&Vec3::new(-local.y, local.x, 0.0)),
+            self.material.clone(),
+        )
+    }
+}
+
+impl Collidable for Torus {
+    fn collision(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Collision> {
+        self.roots(ray)
+            .into_iter()
+            .find(|&t| t > t_min && t < t_max)
+            .map(|t| self.collision_at(ray, t))
+    }
+
+    fn intervals(&self, ray: &Ray) -> Option<Vec<Interval>> {
+        Some(Interval::pairs(
+            self.roots(ray)
+                .into_iter()
+                .map(|t| self.collision_at(ray, t))
+                .collect(),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let ring = Aabb::disk(self.center, self.frame.n, self.major_radius);
+        let tube = Vec3::one() * self.minor_radius;
+        Some(Aabb::new(ring.min - tube, ring.max + tube))
+    }
+}
@@ -0,0 +1,210 @@
+//! Affine transformations.
+use crate::Vec3;
+
+/// Row-major 4x4 matrix.
+pub type Matrix = [[f64; 4]; 4];
+
+/// Identity matrix.
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// Product of two matrices.
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    m
+}
+
+/// Inverse of a matrix by Gauss-Jordan elimination with partial pivoting, if it is invertible.
+fn invert(matrix: &Matrix) -> Option<Matrix> {
+    let mut a = *matrix;
+    let mut inverse = IDENTITY;
+    for column in 0..4 {
+        let pivot = (column..4)
+            .max_by(|&x, &y| {
+                a[x][column]
+                    .abs()
+                    .partial_cmp(&a[y][column].abs())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap();
+        if a[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(column, pivot);
+        inverse.swap(column, pivot);
+        let scale = 1.0 / a[column][column];
+        for j in 0..4 {
+            a[column][j] *= scale;
+            inverse[column][j] *= scale;
+        }
+        for row in 0..4 {
+            if row != column {
+                let factor = a[row][column];
+                for j in 0..4 {
+                    a[row][j] -= factor * a[column][j];
+                    inverse[row][j] -= factor * inverse[column][j];
+                }
+            }
+        }
+    }
+    Some(inverse)
+}
+
+/// Affine transformation of points, vectors and normals, stored with its inverse.
+#[derive(Copy, Clone, Debug)]
+pub struct Transform {
+    matrix: Matrix,
+    inverse: Matrix,
+}
+
+impl Transform {
+    /// Identity transformation.
+    pub fn identity() -> Self {
+        Self {
+            matrix: IDENTITY,
+            inverse: IDENTITY,
+        }
+    }
+
+    /// Transformation by a row-major matrix, if it is invertible.
+    pub fn from_matrix(matrix: Matrix) -> Option<Self> {
+        Some(Self {
+            matrix,
+            inverse: invert(&matrix)?,
+        })
+    }
+
+    /// Translation by an offset.
+    pub fn translate(offset: Vec3) -> Self {
+        let mut matrix = IDENTITY;
+        let mut inverse = IDENTITY;
+        for axis in 0..3 {
+            matrix[axis][3] = offset[axis];
+            inverse[axis][3] = -offset[axis];
+        }
+        Self { matrix, inverse }
+    }
+
+    /// Scale by a factor along each axis.
+    pub fn scale(factor: Vec3) -> Self {
+        let mut matrix = IDENTITY;
+        let mut inverse = IDENTITY;
+        for axis in 0..3 {
+            matrix[axis][axis] = factor[axis];
+            inverse[axis][axis] = 1.0 / factor[axis];
+        }
+        Self { matrix, inverse }
+    }
+
+    /// Counter-clockwise rotation in degrees about an axis through the origin.
+    pub fn rotate(angle: f64, axis: Vec3) -> Self {
+        let a = axis.normalized();
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let matrix = [
+            [
+                a.x * a.x + (1.0 - a.x * a.x) * cos,
+                a.x * a.y * (1.0 - cos) - a.z * sin,
+                a.x * a.z * (1.0 - cos) + a.y * sin,
+                0.0,
+            ],
+            [
+                a.x * a.y * (1.0 - cos) + a.z * sin,
+                a.y * a.y + (1.0 - a.y * a.y) * cos,
+                a.y * a.z * (1.0 - cos) - a.x * sin,
+                0.0,
+            ],
+            [
+                a.x * a.z * (1.0 - cos) - a.y * sin,
+                a.y * a.z * (1.0 - cos) + a.x * sin,
+                a.z * a.z + (1.0 - a.z * a.z) * cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        // Rotations are orthogonal, so the inverse is the transpose.
+        let mut inverse = matrix;
+        for (i, row) in inverse.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = matrix[j][i];
+            }
+        }
+        Self { matrix, inverse }
+    }
+
+    /// Transformation applying `self` and then `other`.
+    pub fn then(&self, other: &Transform) -> Self {
+        Self {
+            matrix: multiply(&other.matrix, &self.matrix),
+            inverse: multiply(&self.inverse, &other.inverse),
+        }
+    }
+
+    /// Inverse transformation.
+    pub fn inverse(&self) -> Self {
+        Self {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    /// Row-major matrix of the transformation.
+    pub fn matrix(&self) -> &Matrix {
+        &self.matrix
+    }
+
+    /// Transform a point.
+    pub fn point(&self, p: &Vec3) -> Vec3 {
+        let m = &self.matrix;
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        Vec3::new(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3],
+        ) / w
+    }
+
+    /// Transform a direction, ignoring translation.
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.matrix;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    /// Transform a surface normal by the inverse transpose, keeping it perpendicular to the
+    /// transformed surface (not normalized).
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        let m = &self.inverse;
+        Vec3::new(
+            m[0][0] * n.x + m[1][0] * n.y + m[2][0] * n.z,
+            m[0][1] * n.x + m[1][1] * n.y + m[2][1] * n.z,
+            m[0][2] * n.x + m[1][2] * n.y + m[2][2] * n.z,
+        )
+    }
+
+    /// Whether the transformation mirrors space, reversing the winding of triangles.
+    pub fn swaps_handedness(&self) -> bool {
+        let m = &self.matrix;
+        let determinant = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+        determinant < 0.0
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
@@ -3,6 +3,7 @@
 //! Operator overloads handled by the extremely convenient
 //! [auto_ops](https://docs.rs/auto_ops/0.1.0/auto_ops/index.html) crate.
 use std::fmt::{Display, Formatter, Result};
+use std::ops::Index;
 
 use auto_ops::{impl_op_ex, impl_op_ex_commutative};
 use rand::Rng;
@@ -55,6 +56,21 @@ impl Vec3 {
         }
     }
 
+    /// Element-wise minimum with another vector.
+    pub fn min(&self, rhs: &Self) -> Self {
+        Self::new(self.x.min(rhs.x), self.y.min(rhs.y), self.z.min(rhs.z))
+    }
+
+    /// Element-wise maximum with another vector.
+    pub fn max(&self, rhs: &Self) -> Self {
+        Self::new(self.x.max(rhs.x), self.y.max(rhs.y), self.z.max(rhs.z))
+    }
+
+    /// Relative luminance of the vector as a linear RGB color.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
     /// Cross product operation with another vector.
     pub fn cross(&self, rhs: &Self) -> Self {
         Self {
@@ -79,6 +95,19 @@ impl Vec3 {
         self / self.magnitude()
     }
 
+    /// Two unit vectors which together with the (unit) vector form an orthonormal basis.
+    ///
+    /// ([source](https://graphics.pixar.com/library/OrthonormalB/paper.pdf))
+    pub fn orthonormal_basis(&self) -> (Self, Self) {
+        let sign = 1.0_f64.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+        (
+            Self::new(1.0 + sign * self.x * self.x * a, sign * b, -sign * self.x),
+            Self::new(b, sign + self.y * self.y * a, -self.y),
+        )
+    }
+
     /// Reflect the vector via a surface normal vector.
     ///
     /// ([source](https://raytracing.github.io/books/RayTracingInOneWeekend.html#metal/mirroredlightreflection))
@@ -114,7 +143,7 @@ impl Vec3 {
     /// Randomized vector with components within a unit disk.
     ///
     /// * `axis` - Denotes the axis normal to unit disk.
-    /// (e.g. X normal to the Y-Z plane, components will be in Y-Z unit disk).
+    ///   (e.g. X normal to the Y-Z plane, components will be in Y-Z unit disk).
     pub fn random_in_unit_disk(axis_normal: Axis) -> Self {
         let mut rng = rand::thread_rng();
         loop {
@@ -223,6 +252,20 @@ impl_op_ex!(-= |a: &mut Vec3, b: &f64 | { *a = *a - b; });
 impl_op_ex!(*= |a: &mut Vec3, b: &f64 | { *a = *a * b; });
 impl_op_ex!(/= |a: &mut Vec3, b: &f64 | { *a = *a / b; });
 
+// Component access by axis index (0, 1, 2 for X, Y, Z).
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &f64 {
+        match index {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("vector index out of range: {}", index),
+        }
+    }
+}
+
 // Formatted display.
 impl Display for Vec3 {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
@@ -1,12 +1,15 @@
 //! Ray tracing world encapsulation.
-use crate::{Camera, Collidable, Color, Vec3};
+use crate::light::AreaLight;
+use crate::{Camera, Collidable, Collision, Color, Environment, FindCollision, Light, Ray, Vec3};
 
 use rand::{Rng, RngCore};
 
 /// Ray tracing world encapsulation.
 pub struct World {
-    sky: Vec3,
+    environment: Box<dyn Environment>,
     collidables: Vec<Box<dyn Collidable>>,
+    lights: Vec<Box<dyn Light>>,
+    area_lights: Vec<AreaLight>,
     t_min: f64,
     t_max: f64,
     max_depth: usize,
@@ -15,16 +18,19 @@ pub struct World {
 
 impl World {
     pub fn new(
-        sky: Vec3,
+        environment: Box<dyn Environment>,
         collidables: Vec<Box<dyn Collidable>>,
+        lights: Vec<Box<dyn Light>>,
         t_min: f64,
         t_max: f64,
         max_depth: usize,
         samples: usize,
     ) -> Self {
         Self {
-            sky,
+            environment,
             collidables,
+            lights,
+            area_lights: Vec::new(),
             t_min,
             t_max,
             max_depth,
@@ -32,6 +38,55 @@ impl World {
         }
     }
 
+    /// Light seen by rays which do not collide.
+    pub fn environment(&self) -> &dyn Environment {
+        self.environment.as_ref()
+    }
+
+    /// Delta light sources.
+    pub fn lights(&self) -> &[Box<dyn Light>] {
+        &self.lights
+    }
+
+    /// Set the lights emitted by surfaces among the collidables.
+    pub fn set_area_lights(&mut self, area_lights: Vec<AreaLight>) -> &mut Self {
+        self.area_lights = area_lights;
+        self
+    }
+
+    /// Lights emitted by surfaces among the collidables.
+    pub fn area_lights(&self) -> &[AreaLight] {
+        &self.area_lights
+    }
+
+    /// Solid angle density with which the area lights are sampled towards a collision seen
+    /// from a point.
+    pub fn area_light_pdf(&self, point: &Vec3, collision: &Collision) -> f64 {
+        self.area_lights
+            .iter()
+            .map(|light| light.pdf(point, collision))
+            .sum()
+    }
+
+    /// Find the closest collision of a ray with the world's collidables.
+    pub fn closest_collision(&self, ray: &Ray) -> Option<Collision> {
+        self.collidables
+            .iter()
+            .find_closest_collision(ray, self.t_min, self.t_max)
+    }
+
+    /// If any collidable blocks a shadow ray before some distance along it.
+    ///
+    /// * `ray` - Shadow ray with a unit direction.
+    /// * `distance` - Distance to the light.
+    pub fn occluded(&self, ray: &Ray, distance: f64) -> bool {
+        let t_max = (distance * (1.0 - 1e-6)).min(self.t_max);
+        self.collidables
+            .iter()
+            .find_closest_collision(ray, self.t_min, t_max)
+            .is_some()
+    }
+
     /// Calculate pixel color given a camera.
     pub fn pixel_color<RNG: RngCore>(
         &self,
@@ -39,24 +94,20 @@ impl World {
         y: usize,
         window_width: usize,
         window_height: usize,
-        camera: &Camera,
+        camera: &dyn Camera,
         rng: &mut RNG,
     ) -> Color {
         let World {
-            sky,
-            collidables,
-            t_min,
-            t_max,
-            max_depth,
-            samples,
+            max_depth, samples, ..
         } = self;
 
         let mut pixel_color = Vec3::zero();
         for _ in 0..(*samples) {
             let u = (x as f64 + rng.gen::<f64>()) / (window_width - 1) as f64;
             let v = (y as f64 + rng.gen::<f64>()) / (window_height - 1) as f64;
-            let ray = camera.get_ray(u, v);
-            pixel_color += ray.color(&sky, &collidables, *t_min, *t_max, *max_depth)
+            if let Some(ray) = camera.get_ray(u, v) {
+                pixel_color += ray.color(self, *max_depth)
+            }
         }
         let scale = 1.0 / (*samples) as f64;
         let r = (scale * pixel_color.x).sqrt();